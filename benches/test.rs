@@ -2,7 +2,8 @@
 
 extern crate test;
 
-use swtch_regexp::automa::{Dfa, Nfa};
+use swtch_regexp::automa::{Builder, Dfa, Nfa};
+use swtch_regexp::vm::{compile, Interpreter, OnePass};
 use test::Bencher;
 
 #[bench]
@@ -24,3 +25,36 @@ fn dfa_test_bench(bench: &mut Bencher) {
         })
     });
 }
+
+#[bench]
+fn bit_parallel_test_bench(bench: &mut Bencher) {
+    let matcher = Builder::new()
+        .build_bit_parallel("abb.+.a.")
+        .unwrap()
+        .unwrap();
+    bench.iter(|| {
+        (0..100).for_each(|_| {
+            matcher.test("aabbbba");
+        })
+    });
+}
+
+#[bench]
+fn backtracking_captures_bench(bench: &mut Bencher) {
+    let interpreter = Interpreter::new(compile("(key=([a-z]+);)").unwrap());
+    bench.iter(|| {
+        (0..100).for_each(|_| {
+            interpreter.backtracking_captures("key=abcdefgh;");
+        })
+    });
+}
+
+#[bench]
+fn one_pass_captures_bench(bench: &mut Bencher) {
+    let one_pass = OnePass::new(&compile("(key=([a-z]+);)").unwrap()).unwrap();
+    bench.iter(|| {
+        (0..100).for_each(|_| {
+            one_pass.captures("key=abcdefgh;");
+        })
+    });
+}
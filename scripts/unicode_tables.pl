@@ -0,0 +1,119 @@
+#!/usr/bin/env perl
+# Generates the tables under src/unicode from the Unicode database shipped
+# with perl:
+#
+#     perl scripts/unicode_tables.pl
+#
+# Run `cargo fmt` afterwards.
+use strict;
+use warnings;
+use Unicode::UCD qw(prop_invlist prop_invmap prop_value_aliases prop_values);
+
+my $version = Unicode::UCD::UnicodeVersion();
+
+sub header {
+    my ($fh) = @_;
+    print $fh "// Generated by scripts/unicode_tables.pl from Unicode $version, do not edit.\n\n";
+}
+
+sub char_literal {
+    my ($cp) = @_;
+    return sprintf("'\\u{%X}'", $cp);
+}
+
+# Simple case folding, as equivalence classes of chars sharing a folding
+sub case_folding {
+    my ($list, $map) = prop_invmap('scf');
+    my %classes;
+    for my $i (0 .. $#$list - 1) {
+        next unless $map->[$i];
+        for my $cp ($list->[$i] .. $list->[$i + 1] - 1) {
+            my $fold = $map->[$i] + $cp - $list->[$i];
+            push @{ $classes{$fold} }, $cp;
+        }
+    }
+    my %others;
+    for my $fold (keys %classes) {
+        my @class = sort { $a <=> $b } ($fold, @{ $classes{$fold} });
+        for my $cp (@class) {
+            $others{$cp} = [grep { $_ != $cp } @class];
+        }
+    }
+
+    open(my $fh, '>', 'src/unicode/case_folding.rs') or die $!;
+    header($fh);
+    print $fh "/// Every char with case variants, mapped to its other variants.\n";
+    print $fh "pub const CASE_FOLDING_SIMPLE: &[(char, &[char])] = &[\n";
+    for my $cp (sort { $a <=> $b } keys %others) {
+        my $variants = join(', ', map { char_literal($_) } @{ $others{$cp} });
+        print $fh '(', char_literal($cp), ", &[$variants]),\n";
+    }
+    print $fh "];\n";
+    close($fh);
+}
+
+case_folding();
+
+sub normalize {
+    my ($name) = @_;
+    $name = lc($name);
+    $name =~ s/[_\- ]//g;
+    return $name;
+}
+
+# Ranges of chars having `$prop=$value`, with the surrogates left out
+sub ranges {
+    my ($prop, $value) = @_;
+    my @list = prop_invlist("$prop=$value");
+    push @list, 0x110000 if @list % 2;
+    my @ranges;
+    while (my ($start, $end) = splice(@list, 0, 2)) {
+        $end -= 1;
+        if ($start < 0xD800 && $end > 0xDFFF) {
+            push @ranges, [$start, 0xD7FF], [0xE000, $end];
+        } elsif ($start >= 0xD800 && $end <= 0xDFFF) {
+            next;
+        } else {
+            $start = 0xE000 if $start >= 0xD800 && $start <= 0xDFFF;
+            $end = 0xD7FF if $end >= 0xD800 && $end <= 0xDFFF;
+            push @ranges, [$start, $end];
+        }
+    }
+    return @ranges;
+}
+
+# One table per value of `$prop`, looked up by any normalized alias
+sub property {
+    my ($prop, $file, $doc) = @_;
+    open(my $fh, '>', "src/unicode/$file.rs") or die $!;
+    header($fh);
+    my %by_name;
+    for my $value (sort(prop_values($prop))) {
+        my @ranges = ranges($prop, $value);
+        next unless @ranges;
+        my ($short, $long) = prop_value_aliases($prop, $value);
+        my $const = uc($long);
+        for my $alias (prop_value_aliases($prop, $value)) {
+            $by_name{ normalize($alias) } = $const;
+        }
+        print $fh "#[rustfmt::skip]\n";
+        print $fh "const $const: &[(char, char)] = &[\n";
+        while (my @line = splice(@ranges, 0, 4)) {
+            print $fh '    ', join(' ', map {
+                '(' . char_literal($_->[0]) . ', ' . char_literal($_->[1]) . '),'
+            } @line), "\n";
+        }
+        print $fh "];\n\n";
+    }
+    print $fh "/// $doc, by normalized alias.\n";
+    print $fh "#[rustfmt::skip]\n";
+    print $fh "pub const BY_NAME: &[(&str, &[(char, char)])] = &[\n";
+    for my $name (sort keys %by_name) {
+        print $fh "    (\"$name\", $by_name{$name}),\n";
+    }
+    print $fh "];\n";
+    close($fh);
+}
+
+property('gc', 'general_category', 'General categories');
+property('sc', 'script', 'Scripts');
@@ -0,0 +1,311 @@
+use std::{
+    collections::HashMap,
+    ops::{BitAnd, BitOr, BitOrAssign, Shr},
+};
+
+use crate::class::CharClass;
+
+use super::{Dfa, Nfa, State};
+
+/// Largest number of positions a [`BitParallel`] matcher handles.
+pub const MAX_POSITIONS: usize = 128;
+
+/// Automaton of at most [`MAX_POSITIONS`] positions simulated in a machine word.
+pub struct BitParallel {
+    engine: Engine,
+}
+
+enum Engine {
+    Word64(Masks<u64>),
+    Word128(Masks<u128>),
+}
+
+impl BitParallel {
+    /// Whether the automaton accepts the whole of `str`.
+    pub fn test(&self, str: &str) -> bool {
+        match &self.engine {
+            Engine::Word64(masks) => masks.test(str),
+            Engine::Word128(masks) => masks.test(str),
+        }
+    }
+
+    /// Number of positions, which is the number of bits in use.
+    pub fn positions(&self) -> usize {
+        match &self.engine {
+            Engine::Word64(masks) => masks.positions,
+            Engine::Word128(masks) => masks.positions,
+        }
+    }
+}
+
+impl Nfa {
+    /// `None` past [`MAX_POSITIONS`] positions.
+    pub fn bit_parallel(&self) -> Option<BitParallel> {
+        let positions = if self.has_epsilons() {
+            Positions::new(&self.simplify())?
+        } else {
+            Positions::new(self)?
+        };
+        let engine = if positions.classes.len() <= 64 {
+            Engine::Word64(Masks::new(&positions))
+        } else {
+            Engine::Word128(Masks::new(&positions))
+        };
+        Some(BitParallel { engine })
+    }
+}
+
+impl Dfa {
+    /// Same as [`Nfa::bit_parallel`].
+    pub fn bit_parallel(&self) -> Option<BitParallel> {
+        self.nfa.bit_parallel()
+    }
+}
+
+/// Epsilon-free automaton whose edges into a position all read one class.
+struct Positions {
+    /// Class read on the way into each position, `None` for the initial one.
+    classes: Vec<Option<CharClass>>,
+    follow: Vec<Vec<usize>>,
+    accepting: Vec<bool>,
+}
+
+impl Positions {
+    /// Splits every state of the epsilon-free `nfa` per class leading to it.
+    fn new(nfa: &Nfa) -> Option<Self> {
+        let mut index: HashMap<(State, Option<&CharClass>), usize> =
+            HashMap::from([((nfa.initial_state, None), 0)]);
+        let mut copies = vec![vec![]; nfa.state_count()];
+        copies[nfa.initial_state as usize].push(0);
+        let mut classes = vec![None];
+        let mut accepting = vec![nfa.is_accepting(nfa.initial_state)];
+        for state in 0..nfa.state_count() as State {
+            for (class, target) in nfa.edges(state) {
+                index.entry((target, class)).or_insert_with(|| {
+                    copies[target as usize].push(classes.len());
+                    classes.push(class.cloned());
+                    accepting.push(nfa.is_accepting(target));
+                    classes.len() - 1
+                });
+            }
+            if classes.len() > MAX_POSITIONS {
+                return None;
+            }
+        }
+        let mut follow = vec![vec![]; classes.len()];
+        for state in 0..nfa.state_count() as State {
+            for (class, target) in nfa.edges(state) {
+                let next = index[&(target, class)];
+                for &position in &copies[state as usize] {
+                    follow[position].push(next);
+                }
+            }
+        }
+        Some(Self {
+            classes,
+            follow,
+            accepting,
+        })
+    }
+}
+
+/// Unsigned integer holding one bit per position.
+trait Word:
+    Copy
+    + Default
+    + Eq
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitOrAssign
+    + Shr<u32, Output = Self>
+{
+    fn bit(i: usize) -> Self;
+    fn low_byte(self) -> usize;
+}
+
+macro_rules! word {
+    ($t:ty) => {
+        impl Word for $t {
+            fn bit(i: usize) -> Self {
+                1 << i
+            }
+
+            fn low_byte(self) -> usize {
+                (self & 0xff) as usize
+            }
+        }
+    };
+}
+
+word!(u64);
+word!(u128);
+
+/// Precomputed words for [`Positions`].
+struct Masks<W> {
+    positions: usize,
+    initial: W,
+    accepting: W,
+    /// Positions following those set in each byte of the active set.
+    follow: Vec<[W; 256]>,
+    /// Positions entered by reading each ASCII char.
+    ascii: Vec<W>,
+    /// Sorted disjoint ranges of chars with the positions they enter.
+    ranges: Vec<(char, char, W)>,
+}
+
+impl<W: Word> Masks<W> {
+    fn new(positions: &Positions) -> Self {
+        let n = positions.classes.len();
+        let word = |set: &mut dyn Iterator<Item = usize>| {
+            set.fold(W::default(), |word, i| word | W::bit(i))
+        };
+
+        let follow = (0..n.div_ceil(8))
+            .map(|chunk| {
+                let mut table = [W::default(); 256];
+                for (byte, entry) in table.iter_mut().enumerate() {
+                    *entry = word(
+                        &mut (0..8)
+                            .filter(|bit| byte & (1 << bit) != 0)
+                            .map(|bit| chunk * 8 + bit)
+                            .filter(|&i| i < n)
+                            .flat_map(|i| positions.follow[i].iter().copied()),
+                    );
+                }
+                table
+            })
+            .collect();
+
+        let pieces = CharClass::partition(positions.classes.iter().flatten());
+        let mut ranges: Vec<_> = pieces
+            .iter()
+            .flat_map(|piece| {
+                let c = piece.ranges()[0].0;
+                let mask = word(&mut (0..n).filter(|&i| {
+                    positions.classes[i]
+                        .as_ref()
+                        .is_some_and(|class| class.contains(c))
+                }));
+                piece
+                    .ranges()
+                    .iter()
+                    .map(move |&(start, end)| (start, end, mask))
+            })
+            .collect();
+        ranges.sort_unstable_by_key(|&(start, _, _)| start);
+
+        let mut masks = Self {
+            positions: n,
+            initial: W::bit(0),
+            accepting: word(&mut (0..n).filter(|&i| positions.accepting[i])),
+            follow,
+            ascii: vec![],
+            ranges,
+        };
+        masks.ascii = ('\0'..='\x7f').map(|c| masks.search(c)).collect();
+        masks
+    }
+
+    fn search(&self, c: char) -> W {
+        match self.ranges.binary_search_by(|&(start, end, _)| {
+            if end < c {
+                std::cmp::Ordering::Less
+            } else if start > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        }) {
+            Ok(i) => self.ranges[i].2,
+            Err(_) => W::default(),
+        }
+    }
+
+    fn mask(&self, c: char) -> W {
+        match self.ascii.get(c as usize) {
+            Some(&mask) => mask,
+            None => self.search(c),
+        }
+    }
+
+    /// Positions following any of `active`.
+    fn step(&self, mut active: W) -> W {
+        let mut next = W::default();
+        for table in &self.follow {
+            if active == W::default() {
+                break;
+            }
+            next |= table[active.low_byte()];
+            active = active >> 8;
+        }
+        next
+    }
+
+    fn test(&self, str: &str) -> bool {
+        let mut active = self.initial;
+        for c in str.chars() {
+            active = self.step(active) & self.mask(c);
+            if active == W::default() {
+                return false;
+            }
+        }
+        active & self.accepting != W::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Builder;
+
+    use super::*;
+
+    #[test]
+    fn test_bit_parallel() {
+        let matcher = Builder::new()
+            .build_bit_parallel("ab|*a.b.b.")
+            .unwrap()
+            .unwrap();
+        assert_eq!(matcher.positions(), 6);
+        assert!(matcher.test("abb"));
+        assert!(matcher.test("babaabb"));
+        assert!(!matcher.test("abba"));
+        assert!(!matcher.test(""));
+
+        let matcher = Builder::new()
+            .case_insensitive(true)
+            .classes(true)
+            .build_bit_parallel(r"\p{Greek}+[0-9]?.")
+            .unwrap()
+            .unwrap();
+        assert!(matcher.test("λόγος1"));
+        assert!(matcher.test("ΛΌΓΟΣ"));
+        assert!(!matcher.test("logos"));
+    }
+
+    #[test]
+    fn test_from_any_automaton() {
+        for expr in ["ab|*a.b.b.", "ab.+c?.", "a*b*|c+.", "a**b+*."] {
+            let thompson = Nfa::from_str(expr).unwrap();
+            let matcher = thompson.bit_parallel().unwrap();
+            let dfa = Dfa::from_str(expr).unwrap();
+            for input in ["", "a", "ab", "abb", "abab", "abcab", "aabbb", "ccc", "bba"] {
+                assert_eq!(matcher.test(input), thompson.test(input), "{expr} {input}");
+            }
+            // Product automata have several classes into the same state
+            let matcher = dfa.union(&dfa).bit_parallel().unwrap();
+            assert_eq!(matcher.test("abb"), thompson.test("abb"), "{expr}");
+        }
+    }
+
+    #[test]
+    fn test_word_size() {
+        let expr = format!("a{}", "b.".repeat(100));
+        let matcher = Builder::new().build_bit_parallel(&expr).unwrap().unwrap();
+        assert_eq!(matcher.positions(), 102);
+        assert!(matcher.test(&format!("a{}", "b".repeat(100))));
+        assert!(!matcher.test(&format!("a{}", "b".repeat(99))));
+
+        let expr = format!("a{}", "b.".repeat(127));
+        assert!(Builder::new().build_bit_parallel(&expr).unwrap().is_none());
+    }
+}
@@ -0,0 +1,73 @@
+use super::{error::NfaError, glushkov::Construction, nfa::Node, BitParallel, Dfa, Nfa};
+
+/// Configures how a postfix pattern is turned into an automaton.
+#[derive(Debug, Clone, Default)]
+pub struct Builder {
+    pub(super) case_insensitive: bool,
+    pub(super) classes: bool,
+    construction: Construction,
+    max_states: Option<usize>,
+    dfa_cache_capacity: Option<usize>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses Unicode simple case folding.
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut Self {
+        self.case_insensitive = yes;
+        self
+    }
+
+    /// Parses bracket classes such as `[^a-z]`, where `[` is a plain char
+    /// otherwise.
+    pub fn classes(&mut self, yes: bool) -> &mut Self {
+        self.classes = yes;
+        self
+    }
+
+    /// Selects how the automaton is built from the parsed pattern.
+    pub fn construction(&mut self, construction: Construction) -> &mut Self {
+        self.construction = construction;
+        self
+    }
+
+    /// Rejects patterns whose automaton has more than `limit` states.
+    pub fn max_states(&mut self, limit: usize) -> &mut Self {
+        self.max_states = Some(limit);
+        self
+    }
+
+    /// See [`Dfa::with_cache_capacity`].
+    pub fn dfa_cache_capacity(&mut self, bytes: usize) -> &mut Self {
+        self.dfa_cache_capacity = Some(bytes);
+        self
+    }
+
+    pub fn build_nfa(&self, expr: &str) -> Result<Nfa, NfaError> {
+        let nodes = Node::parse(expr, self)?;
+        match self.construction {
+            Construction::Thompson => Nfa::thompson(nodes, self.max_states),
+            Construction::Glushkov => Nfa::glushkov(nodes, self.max_states),
+        }
+    }
+
+    pub fn build_dfa(&self, expr: &str) -> Result<Dfa, NfaError> {
+        let dfa = Dfa::new(self.build_nfa(expr)?);
+        Ok(match self.dfa_cache_capacity {
+            Some(bytes) => dfa.with_cache_capacity(bytes),
+            None => dfa,
+        })
+    }
+
+    /// Always Glushkov, `None` past [`MAX_POSITIONS`](super::MAX_POSITIONS) - 1 literals.
+    pub fn build_bit_parallel(&self, expr: &str) -> Result<Option<BitParallel>, NfaError> {
+        let nfa = self
+            .clone()
+            .construction(Construction::Glushkov)
+            .build_nfa(expr)?;
+        Ok(nfa.bit_parallel())
+    }
+}
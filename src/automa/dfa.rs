@@ -1,21 +1,52 @@
-use std::{collections::HashMap, sync::Mutex};
+use std::{
+    borrow::Cow,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        OnceLock,
+    },
+};
 
-use petgraph::visit::EdgeRef;
-
-use super::{error::NfaError, Nfa, State};
+use super::{error::NfaError, Builder, Nfa, State};
 
+/// Automaton simulated over sets of states, caching the transitions. The
+/// cache is filled without locking, so a shared `Dfa` matches concurrently.
 pub struct Dfa {
-    nfa: Nfa,
-    next_state_cache: Mutex<HashMap<State, Vec<State>>>,
+    pub(super) nfa: Nfa,
+    /// Epsilon closures, computed on first use.
+    next_states: Vec<OnceLock<Box<[State]>>>,
+    /// Estimate of the memory taken by the cached states.
+    cache_bytes: AtomicUsize,
+    cache_capacity: usize,
 }
 
 impl Dfa {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(expr: &str) -> Result<Dfa, NfaError> {
-        let dfa = Self {
-            nfa: Nfa::from_str(expr)?,
-            next_state_cache: Mutex::new(HashMap::new()),
-        };
-        Ok(dfa)
+        Builder::new().build_dfa(expr)
+    }
+}
+
+impl Dfa {
+    pub fn new(nfa: Nfa) -> Self {
+        Self {
+            next_states: (0..nfa.state_count()).map(|_| OnceLock::new()).collect(),
+            nfa,
+            cache_bytes: AtomicUsize::new(0),
+            cache_capacity: usize::MAX,
+        }
+    }
+
+    /// Past about `bytes` bytes, new transitions are no longer cached.
+    pub fn with_cache_capacity(mut self, bytes: usize) -> Self {
+        self.cache_capacity = bytes;
+        self
+    }
+}
+
+impl Dfa {
+    /// See [`Nfa::reverse`].
+    pub fn reverse(&self) -> Dfa {
+        Dfa::new(self.nfa.reverse())
     }
 }
 
@@ -26,36 +57,42 @@ impl Dfa {
 }
 
 impl Dfa {
-    fn get_next_states(&self, cur: State) -> Vec<State> {
-        let mut next_state_cache = self.next_state_cache.lock().unwrap();
-        if let Some(states) = next_state_cache.get(&cur) {
-            return states.clone();
+    fn get_next_states(&self, cur: State) -> Cow<'_, [State]> {
+        let cell = &self.next_states[cur as usize];
+        if let Some(states) = cell.get() {
+            return Cow::Borrowed(states);
         }
         let states = self.nfa.get_next_states(cur);
 
-        next_state_cache.insert(cur, states.clone());
-        states
+        let bytes = size_of_val(&states[..]);
+        let cached = self.cache_bytes.fetch_add(bytes, Ordering::Relaxed);
+        if cached.saturating_add(bytes) > self.cache_capacity {
+            self.cache_bytes.fetch_sub(bytes, Ordering::Relaxed);
+            return Cow::Owned(states);
+        }
+        if cell.set(states.into_boxed_slice()).is_err() {
+            // Another thread cached them first
+            self.cache_bytes.fetch_sub(bytes, Ordering::Relaxed);
+        }
+        Cow::Borrowed(cell.get().unwrap())
     }
 
     pub fn test(&self, str: &str) -> bool {
         let nfa = &self.nfa;
-        let mut current_states = self.get_next_states(nfa.initial_state);
+        let mut current_states = self.get_next_states(nfa.initial_state).into_owned();
+        let mut next_states = Vec::new();
         for c in str.chars() {
-            let mut next_states = Vec::new();
-            for state in current_states {
-                for edge in nfa.graph.edges(state) {
-                    if let Some(c_) = edge.weight() {
-                        if c == *c_ {
-                            next_states.extend(self.get_next_states(edge.target()));
-                        }
+            for &state in &current_states {
+                for (class, target) in nfa.class_edges(state) {
+                    if class.contains(c) {
+                        next_states.extend_from_slice(&self.get_next_states(target));
                     }
                 }
             }
-            current_states = next_states;
+            std::mem::swap(&mut current_states, &mut next_states);
+            next_states.clear();
         }
-        current_states
-            .iter()
-            .any(|&s| nfa.accepted_states.contains(&s))
+        current_states.iter().any(|&s| nfa.is_accepting(s))
     }
 }
 
@@ -75,4 +112,41 @@ mod test {
         assert!(dfa.test("abab"));
         assert!(dfa.test("abbb"));
     }
+
+    #[test]
+    fn test_reverse() {
+        let dfa = Dfa::from_str("ab|c.").unwrap().reverse();
+        assert!(dfa.test("ca"));
+        assert!(dfa.test("cb"));
+        assert!(!dfa.test("ac"));
+    }
+
+    #[test]
+    fn test_cache_capacity() {
+        let dfa = Builder::new()
+            .dfa_cache_capacity(8)
+            .build_dfa("abb.+.a.")
+            .unwrap();
+        assert!(dfa.test("abbbbbbbba"));
+        assert!(!dfa.test("abbb"));
+        assert!(dfa.cache_bytes.load(Ordering::Relaxed) <= 8);
+    }
+
+    #[test]
+    fn test_shared() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Dfa>();
+
+        let dfa = Dfa::from_str("abb.+.a.").unwrap();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..100 {
+                        assert!(dfa.test("abbbba"));
+                        assert!(!dfa.test("abbbb"));
+                    }
+                });
+            }
+        });
+    }
 }
@@ -0,0 +1,468 @@
+use std::collections::BTreeMap;
+
+use crate::class::CharClass;
+
+use super::{Dfa, Nfa, State};
+
+impl Nfa {
+    /// Infix pattern for `vm::compile` matching the accepted strings, `None`
+    /// when it would be empty.
+    pub fn to_pattern(&self) -> Option<String> {
+        match self.to_regex() {
+            Regex::Empty | Regex::Epsilon => None,
+            regex => Some(format!("(?:{})", regex.infix())),
+        }
+    }
+
+    /// Postfix pattern for a `Builder::classes` parser, `None` when it would
+    /// be empty.
+    pub fn to_postfix(&self) -> Option<String> {
+        match self.to_regex() {
+            Regex::Empty | Regex::Epsilon => None,
+            regex => Some(regex.postfix()),
+        }
+    }
+
+    /// State elimination down to a new initial and a new final state.
+    fn to_regex(&self) -> Regex {
+        let n = self.state_count();
+        let (initial, last) = (n, n + 1);
+        let mut edges = Edges::new();
+        edges.add(initial, self.initial_state as usize, Regex::Epsilon);
+        for state in self.accepted_states() {
+            edges.add(state as usize, last, Regex::Epsilon);
+        }
+        for state in 0..n {
+            for (class, target) in self.edges(state as State) {
+                let regex = match class {
+                    Some(class) => Regex::Class(class.clone()),
+                    None => Regex::Epsilon,
+                };
+                edges.add(state, target as usize, regex);
+            }
+        }
+
+        let mut remaining: Vec<usize> = (0..n).collect();
+        while !remaining.is_empty() {
+            // Cheapest state first, to keep the expressions small
+            let (i, &k) = remaining
+                .iter()
+                .enumerate()
+                .min_by_key(|&(_, &k)| edges.incoming(k).len() * edges.outgoing(k).len())
+                .unwrap();
+            remaining.swap_remove(i);
+            edges.eliminate(k);
+        }
+        edges.0.remove(&(initial, last)).unwrap_or(Regex::Empty)
+    }
+}
+
+impl Dfa {
+    /// Infix pattern for `vm::compile` matching the accepted strings, `None`
+    /// when it would be empty.
+    pub fn to_pattern(&self) -> Option<String> {
+        self.nfa.to_pattern()
+    }
+
+    /// Postfix pattern for a `Builder::classes` parser, `None` when it would
+    /// be empty.
+    pub fn to_postfix(&self) -> Option<String> {
+        self.nfa.to_postfix()
+    }
+}
+
+/// Edge label of a generalized automaton. The constructors keep it
+/// simplified, so `Empty` and `Epsilon` only appear at the top.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Regex {
+    /// Matches nothing.
+    Empty,
+    /// Matches the empty string.
+    Epsilon,
+    Class(CharClass),
+    Concat(Vec<Regex>),
+    Alt(Vec<Regex>),
+    Star(Box<Regex>),
+}
+
+/// Edges between states, keyed in order so the output is deterministic.
+struct Edges(BTreeMap<(usize, usize), Regex>);
+
+impl Edges {
+    fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    fn add(&mut self, from: usize, to: usize, regex: Regex) {
+        let old = self.0.remove(&(from, to)).unwrap_or(Regex::Empty);
+        self.0.insert((from, to), alt([old, regex]));
+    }
+
+    fn incoming(&self, k: usize) -> Vec<usize> {
+        self.0
+            .keys()
+            .filter(|&&(from, to)| to == k && from != k)
+            .map(|&(from, _)| from)
+            .collect()
+    }
+
+    fn outgoing(&self, k: usize) -> Vec<usize> {
+        self.0
+            .keys()
+            .filter(|&&(from, to)| from == k && to != k)
+            .map(|&(_, to)| to)
+            .collect()
+    }
+
+    /// Removes state `k`, routing every path through it around it.
+    fn eliminate(&mut self, k: usize) {
+        let looping = self.0.remove(&(k, k)).map_or(Regex::Epsilon, star);
+        let incoming: Vec<_> = self
+            .incoming(k)
+            .into_iter()
+            .map(|from| (from, self.0.remove(&(from, k)).unwrap()))
+            .collect();
+        let outgoing: Vec<_> = self
+            .outgoing(k)
+            .into_iter()
+            .map(|to| (to, self.0.remove(&(k, to)).unwrap()))
+            .collect();
+        for (from, before) in &incoming {
+            for (to, after) in &outgoing {
+                let regex = concat([before.clone(), looping.clone(), after.clone()]);
+                self.add(*from, *to, regex);
+            }
+        }
+    }
+}
+
+fn alt(items: impl IntoIterator<Item = Regex>) -> Regex {
+    let mut class: Option<CharClass> = None;
+    let mut rest: Vec<Regex> = vec![];
+    let mut epsilon = false;
+    let mut stack: Vec<Regex> = items.into_iter().collect();
+    stack.reverse();
+    while let Some(item) = stack.pop() {
+        match item {
+            Regex::Empty => {}
+            Regex::Epsilon => epsilon = true,
+            Regex::Alt(items) => stack.extend(items.into_iter().rev()),
+            Regex::Class(c) => {
+                class = Some(class.map_or(c.clone(), |class| class.union(&c)));
+            }
+            regex => {
+                if !rest.contains(&regex) {
+                    rest.push(regex);
+                }
+            }
+        }
+    }
+    let mut items: Vec<Regex> = class.map(Regex::Class).into_iter().chain(rest).collect();
+    if epsilon && !items.iter().any(nullable) {
+        // `r|r+` reads better as `r*`
+        if let [regex] = &items[..] {
+            if let Some(inner) = plus_operand(regex) {
+                return Regex::Star(Box::new(inner.clone()));
+            }
+        }
+        items.push(Regex::Epsilon);
+    }
+    match items.len() {
+        0 => Regex::Empty,
+        1 => items.pop().unwrap(),
+        _ => Regex::Alt(items),
+    }
+}
+
+fn concat(items: impl IntoIterator<Item = Regex>) -> Regex {
+    let mut flat = vec![];
+    for item in items {
+        match item {
+            Regex::Empty => return Regex::Empty,
+            Regex::Epsilon => {}
+            Regex::Concat(items) => flat.extend(items),
+            regex => flat.push(regex),
+        }
+    }
+    match flat.len() {
+        0 => Regex::Epsilon,
+        1 => flat.pop().unwrap(),
+        _ => Regex::Concat(flat),
+    }
+}
+
+fn star(regex: Regex) -> Regex {
+    match regex {
+        Regex::Empty | Regex::Epsilon => Regex::Epsilon,
+        Regex::Star(_) => regex,
+        // `(r|)*` and `(rr*)*` are both `r*`
+        Regex::Alt(items) if items.contains(&Regex::Epsilon) => {
+            star(alt(items.into_iter().filter(|r| *r != Regex::Epsilon)))
+        }
+        regex => match plus_operand(&regex) {
+            Some(inner) => Regex::Star(Box::new(inner.clone())),
+            None => Regex::Star(Box::new(regex)),
+        },
+    }
+}
+
+fn nullable(regex: &Regex) -> bool {
+    match regex {
+        Regex::Empty | Regex::Class(_) => false,
+        Regex::Epsilon | Regex::Star(_) => true,
+        Regex::Concat(items) => items.iter().all(nullable),
+        Regex::Alt(items) => items.iter().any(nullable),
+    }
+}
+
+/// `r` if `regex` is `rr*`.
+fn plus_operand(regex: &Regex) -> Option<&Regex> {
+    match regex {
+        Regex::Concat(items) => match plus_runs(items)[..] {
+            [(inner, true)] => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Items of a concatenation with each `rr*` run folded into `(r, true)`.
+fn plus_runs(items: &[Regex]) -> Vec<(&Regex, bool)> {
+    let mut runs: Vec<(&Regex, bool)> = vec![];
+    for item in items {
+        if let Regex::Star(inner) = item {
+            let seq = match &**inner {
+                Regex::Concat(seq) => &seq[..],
+                regex => std::slice::from_ref(regex),
+            };
+            if let Some(start) = runs.len().checked_sub(seq.len()) {
+                let run = &runs[start..];
+                if run.iter().zip(seq).all(|(&(r, plus), s)| !plus && r == s) {
+                    runs.truncate(start);
+                    runs.push((inner, true));
+                    continue;
+                }
+            }
+        }
+        runs.push((item, false));
+    }
+    runs
+}
+
+/// Splits the alternatives of `items` from an empty one, if any.
+fn optional(items: &[Regex]) -> (Regex, bool) {
+    let rest: Vec<_> = items
+        .iter()
+        .filter(|&r| *r != Regex::Epsilon)
+        .cloned()
+        .collect();
+    let optional = rest.len() < items.len();
+    let regex = match rest.len() {
+        1 => rest.into_iter().next().unwrap(),
+        _ => Regex::Alt(rest),
+    };
+    (regex, optional)
+}
+
+impl Regex {
+    fn infix(&self) -> String {
+        match self {
+            Regex::Empty | Regex::Epsilon => unreachable!("only found at the top"),
+            Regex::Class(class) => infix_class(class),
+            Regex::Alt(items) => match optional(items) {
+                (regex, true) => format!("{}?", regex.infix_atom()),
+                (_, false) => {
+                    let items: Vec<_> = items.iter().map(Regex::infix).collect();
+                    items.join("|")
+                }
+            },
+            Regex::Concat(items) => {
+                let mut out = String::new();
+                for (item, plus) in plus_runs(items) {
+                    match item {
+                        _ if plus => out += &format!("{}+", item.infix_atom()),
+                        Regex::Alt(items) if !items.contains(&Regex::Epsilon) => {
+                            out += &format!("(?:{})", item.infix());
+                        }
+                        _ => out += &item.infix(),
+                    }
+                }
+                out
+            }
+            Regex::Star(regex) => format!("{}*", regex.infix_atom()),
+        }
+    }
+
+    /// Infix form that a quantifier can follow.
+    fn infix_atom(&self) -> String {
+        match self {
+            Regex::Class(_) => self.infix(),
+            _ => format!("(?:{})", self.infix()),
+        }
+    }
+
+    fn postfix(&self) -> String {
+        match self {
+            Regex::Empty | Regex::Epsilon => unreachable!("only found at the top"),
+            Regex::Class(class) => postfix_class(class),
+            Regex::Alt(items) => match optional(items) {
+                (regex, true) => format!("{}?", regex.postfix()),
+                (_, false) => {
+                    let mut out = items[0].postfix();
+                    for item in &items[1..] {
+                        out += &format!("{}|", item.postfix());
+                    }
+                    out
+                }
+            },
+            Regex::Concat(items) => {
+                let factors: Vec<_> = plus_runs(items)
+                    .into_iter()
+                    .map(|(item, plus)| match plus {
+                        true => format!("{}+", item.postfix()),
+                        false => item.postfix(),
+                    })
+                    .collect();
+                let mut out = factors[0].clone();
+                for factor in &factors[1..] {
+                    out += &format!("{}.", factor);
+                }
+                out
+            }
+            Regex::Star(regex) => format!("{}*", regex.postfix()),
+        }
+    }
+}
+
+fn infix_class(class: &CharClass) -> String {
+    if let Some(c) = class.single() {
+        return escape(c, "\\()|?*+.[]^$");
+    }
+    if *class == CharClass::any() {
+        return "(?s:.)".to_string();
+    }
+    if *class == CharClass::from_char('\n').negate() {
+        return ".".to_string();
+    }
+    bracket(class)
+}
+
+fn postfix_class(class: &CharClass) -> String {
+    match class.single() {
+        Some(c) => match c {
+            '.' | '|' | '?' | '*' | '+' | '\\' | '[' => format!("\\{}", c),
+            c => c.to_string(),
+        },
+        None => bracket(class),
+    }
+}
+
+/// Bracket class such as `[a-z]`, negated when that is shorter.
+fn bracket(class: &CharClass) -> String {
+    let negated = class.negate();
+    let (class, prefix) = if negated.ranges().len() < class.ranges().len() {
+        (&negated, "[^")
+    } else {
+        (class, "[")
+    };
+    let mut out = prefix.to_string();
+    for &(start, end) in class.ranges() {
+        out += &escape(start, "\\[]^-");
+        if end != start {
+            if end as u32 != start as u32 + 1 {
+                out.push('-');
+            }
+            out += &escape(end, "\\[]^-");
+        }
+    }
+    out.push(']');
+    out
+}
+
+fn escape(c: char, special: &str) -> String {
+    match c {
+        '\n' => "\\n".to_string(),
+        '\r' => "\\r".to_string(),
+        '\t' => "\\t".to_string(),
+        c if special.contains(c) => format!("\\{}", c),
+        c => c.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        automa::Builder,
+        vm::{compile, Interpreter},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_to_pattern() {
+        let nfa = Nfa::from_str("ab|*").unwrap();
+        assert_eq!(nfa.to_pattern().unwrap(), "(?:[ab]*)");
+        let nfa = Nfa::from_str("ab+.c?.").unwrap();
+        assert_eq!(nfa.to_pattern().unwrap(), "(?:ab+c?)");
+        let nfa = Builder::new()
+            .classes(true)
+            .build_nfa("\\.\\+|[^\n]*.")
+            .unwrap();
+        assert_eq!(nfa.to_pattern().unwrap(), r"(?:[+.].*)");
+        let dfa = Dfa::from_str("a").unwrap();
+        assert_eq!(dfa.difference(&dfa).to_pattern(), None);
+        assert_eq!(dfa.difference(&dfa).to_postfix(), None);
+        let empty = Dfa::from_str("a*")
+            .unwrap()
+            .difference(&Dfa::from_str("aa*.").unwrap());
+        assert_eq!(empty.to_pattern(), None);
+        assert_eq!(empty.to_postfix(), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for expr in [
+            "ab|*a.b.",
+            "ab.ac.|",
+            "a*b*|c+.",
+            r"\.\[.[^-a-c]?.",
+            "ab.+a?.b*|",
+        ] {
+            let builder = Builder::new().classes(true).clone();
+            let dfa = builder.build_dfa(expr).unwrap();
+            let postfix = dfa.to_postfix().unwrap();
+            assert!(
+                dfa.is_equivalent(&builder.build_dfa(&postfix).unwrap())
+                    .is_yes(),
+                "{expr} -> {postfix}"
+            );
+
+            let pattern = format!("(^{}$)", dfa.to_pattern().unwrap());
+            let interpreter = Interpreter::new(compile(&pattern).unwrap());
+            for string in dfa.strings().take(30) {
+                assert!(interpreter.thompson_vm(&string), "{pattern} on {string:?}");
+            }
+            let alphabet = CharClass::new([('a', 'd'), ('-', '.'), ('[', '[')]);
+            for string in dfa.complement(&alphabet).strings().take(30) {
+                assert!(!interpreter.thompson_vm(&string), "{pattern} on {string:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_simplification() {
+        let a = || Regex::Class(CharClass::from_char('a'));
+        let plus = concat([a(), star(a())]);
+        assert_eq!(alt([Regex::Epsilon, plus.clone()]), star(a()));
+        assert_eq!(star(alt([Regex::Epsilon, a()])), star(a()));
+        assert_eq!(star(plus), star(a()));
+        let ab = || concat([a(), Regex::Class(CharClass::from_char('b'))]);
+        assert_eq!(concat([ab(), star(ab())]).infix(), "(?:ab)+");
+        assert_eq!(star(concat([ab(), star(ab())])), star(ab()));
+        assert_eq!(concat([Regex::Epsilon, a(), Regex::Empty]), Regex::Empty);
+        assert_eq!(
+            alt([a(), Regex::Class(CharClass::from_char('b'))]).infix(),
+            "[ab]"
+        );
+    }
+}
@@ -4,4 +4,8 @@ use thiserror::Error;
 pub enum NfaError {
     #[error("invalid regex: {0}")]
     InvalidRegex(String),
+    #[error("unknown Unicode property: {0:?}")]
+    UnknownProperty(String),
+    #[error("automaton has more than {0} states")]
+    TooManyStates(usize),
 }
@@ -0,0 +1,369 @@
+use crate::class::CharClass;
+
+use super::{
+    product::{edge_classes, Product},
+    Dfa, Nfa,
+};
+
+impl Nfa {
+    /// Shortest accepted string, the smallest one among those of equal length.
+    pub fn shortest_string(&self) -> Option<String> {
+        self.strings().next()
+    }
+
+    /// Endless stream of accepted strings picked at random from `seed`, empty
+    /// if no string is accepted. At each state the walk stops, if the state
+    /// accepts, or picks one of its ranges, all options being equally likely,
+    /// then a char of the range uniformly.
+    pub fn random_strings(&self, seed: u64) -> RandomStrings {
+        RandomStrings {
+            table: Table::new(self),
+            state: seed,
+        }
+    }
+
+    /// Accepted strings in length-lexicographic order.
+    pub fn strings(&self) -> Strings {
+        let table = Table::new(self);
+        Strings {
+            layers: vec![table.accepting.clone()],
+            frontier: vec![0].into_iter().filter(|&s| table.live[s]).collect(),
+            table,
+            len: 0,
+            path: None,
+        }
+    }
+
+    /// `None` on overflow.
+    pub fn count_strings(&self, len: usize) -> Option<u128> {
+        let table = Table::new(self);
+        let mut counts: Vec<u128> = table.accepting.iter().map(|&a| a as u128).collect();
+        for _ in 0..len {
+            counts = table
+                .transitions
+                .iter()
+                .map(|row| {
+                    row.iter().try_fold(0_u128, |sum, &(start, end, target)| {
+                        size(start, end)
+                            .checked_mul(counts[target])?
+                            .checked_add(sum)
+                    })
+                })
+                .collect::<Option<_>>()?;
+        }
+        Some(counts[0])
+    }
+}
+
+impl Dfa {
+    /// Shortest accepted string, the smallest one among those of equal length.
+    pub fn shortest_string(&self) -> Option<String> {
+        self.nfa.shortest_string()
+    }
+
+    /// Endless stream of accepted strings picked at random from `seed`.
+    pub fn random_strings(&self, seed: u64) -> RandomStrings {
+        self.nfa.random_strings(seed)
+    }
+
+    /// Accepted strings in length-lexicographic order.
+    pub fn strings(&self) -> Strings {
+        self.nfa.strings()
+    }
+
+    /// `None` on overflow.
+    pub fn count_strings(&self, len: usize) -> Option<u128> {
+        self.nfa.count_strings(len)
+    }
+}
+
+/// Deterministic transitions of the live states, state 0 being the initial.
+struct Table {
+    /// Char ranges sorted by start, with their target.
+    transitions: Vec<Vec<(char, char, usize)>>,
+    accepting: Vec<bool>,
+    live: Vec<bool>,
+}
+
+impl Table {
+    fn new(nfa: &Nfa) -> Self {
+        let product = Product::new(&[nfa], CharClass::partition(edge_classes(nfa)));
+        let alphabet = product.alphabet();
+        let (states, rows) = product.explore();
+        let accepting: Vec<bool> = states.iter().map(|s| product.accepted(s)[0]).collect();
+
+        let mut live = accepting.clone();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (i, row) in rows.iter().enumerate() {
+                if !live[i] && row.iter().any(|&target| live[target]) {
+                    live[i] = true;
+                    changed = true;
+                }
+            }
+        }
+
+        let transitions = rows
+            .iter()
+            .map(|row| {
+                let mut ranges: Vec<_> = row
+                    .iter()
+                    .zip(alphabet)
+                    .filter(|(&target, _)| live[target])
+                    .flat_map(|(&target, class)| {
+                        class.ranges().iter().map(move |&(s, e)| (s, e, target))
+                    })
+                    .collect();
+                ranges.sort_unstable();
+                ranges
+            })
+            .collect();
+        Self {
+            transitions,
+            accepting,
+            live,
+        }
+    }
+}
+
+/// Iterator returned by [`Nfa::random_strings`].
+pub struct RandomStrings {
+    table: Table,
+    state: u64,
+}
+
+impl RandomStrings {
+    /// SplitMix64 step.
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: u128) -> u128 {
+        let r = ((self.next_u64() as u128) << 64) | self.next_u64() as u128;
+        r % n
+    }
+}
+
+impl Iterator for RandomStrings {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        if !self.table.live[0] {
+            return None;
+        }
+        let mut string = String::new();
+        let mut state = 0;
+        loop {
+            let len = self.table.transitions[state].len();
+            let accepting = self.table.accepting[state];
+            let choice = self.below(len as u128 + accepting as u128) as usize;
+            if choice == len {
+                return Some(string);
+            }
+            let (start, end, target) = self.table.transitions[state][choice];
+            let offset = self.below(size(start, end)) as u32;
+            string.push(nth_char(start, offset));
+            state = target;
+        }
+    }
+}
+
+/// Iterator returned by [`Nfa::strings`].
+pub struct Strings {
+    table: Table,
+    /// `layers[r][s]`: state `s` accepts some string of exactly `r` chars.
+    layers: Vec<Vec<bool>>,
+    /// Live states reachable with exactly `len` chars.
+    frontier: Vec<usize>,
+    len: usize,
+    /// Current string of length `len` as `(state, range index, char)` steps.
+    path: Option<Vec<(usize, usize, char)>>,
+}
+
+impl Strings {
+    /// Smallest step from `state` that can still accept `rest` more chars.
+    fn first_step(
+        &self,
+        state: usize,
+        index: usize,
+        from: char,
+        rest: usize,
+    ) -> Option<(usize, char)> {
+        let row = &self.table.transitions[state];
+        (index..row.len()).find_map(|i| {
+            let (start, end, target) = row[i];
+            let c = if i == index { from.max(start) } else { start };
+            (c <= end && self.layers[rest][target]).then_some((i, c))
+        })
+    }
+
+    /// Completes `path` up to `len` chars with the smallest steps.
+    fn descend(&self, path: &mut Vec<(usize, usize, char)>) {
+        while path.len() < self.len {
+            let state = path
+                .last()
+                .map_or(0, |&(s, i, _)| self.table.transitions[s][i].2);
+            let rest = self.len - path.len() - 1;
+            let (i, c) = self.first_step(state, 0, '\0', rest).unwrap();
+            path.push((state, i, c));
+        }
+    }
+
+    /// Moves to the next string of the same length, if any.
+    fn advance(&self, path: &mut Vec<(usize, usize, char)>) -> bool {
+        while let Some((state, i, c)) = path.pop() {
+            let rest = self.len - path.len() - 1;
+            let next = match next_char(c) {
+                Some(c) => self.first_step(state, i, c, rest),
+                None => self.first_step(state, i + 1, '\0', rest),
+            };
+            if let Some((i, c)) = next {
+                path.push((state, i, c));
+                self.descend(path);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Returns `false` once no longer string can be accepted.
+    fn grow(&mut self) -> bool {
+        let table = &self.table;
+        let mut frontier: Vec<usize> = self
+            .frontier
+            .iter()
+            .flat_map(|&s| table.transitions[s].iter().map(|&(_, _, t)| t))
+            .collect();
+        frontier.sort_unstable();
+        frontier.dedup();
+        self.frontier = frontier;
+        self.len += 1;
+        let last = self.layers.last().unwrap();
+        let layer = table
+            .transitions
+            .iter()
+            .map(|row| row.iter().any(|&(_, _, target)| last[target]))
+            .collect();
+        self.layers.push(layer);
+        !self.frontier.is_empty()
+    }
+}
+
+impl Iterator for Strings {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            let mut path = match self.path.take() {
+                Some(mut path) => {
+                    if self.advance(&mut path) {
+                        path
+                    } else if self.grow() {
+                        continue;
+                    } else {
+                        return None;
+                    }
+                }
+                None if self.frontier.is_empty() => return None,
+                None if self.layers[self.len][0] => vec![],
+                None if self.grow() => continue,
+                None => return None,
+            };
+            self.descend(&mut path);
+            let string = path.iter().map(|&(_, _, c)| c).collect();
+            self.path = Some(path);
+            return Some(string);
+        }
+    }
+}
+
+/// Number of chars in `start..=end`, without the surrogate gap.
+fn size(start: char, end: char) -> u128 {
+    let gap = if start <= '\u{D7FF}' && end >= '\u{E000}' {
+        0x800
+    } else {
+        0
+    };
+    (end as u32 - start as u32 + 1 - gap) as u128
+}
+
+fn nth_char(start: char, offset: u32) -> char {
+    let c = start as u32 + offset;
+    if start <= '\u{D7FF}' && c > 0xD7FF {
+        char::from_u32(c + 0x800).unwrap()
+    } else {
+        char::from_u32(c).unwrap()
+    }
+}
+
+fn next_char(c: char) -> Option<char> {
+    match c {
+        '\u{D7FF}' => Some('\u{E000}'),
+        _ => char::from_u32(c as u32 + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shortest_string() {
+        let nfa = Nfa::from_str("ab|+c.").unwrap();
+        assert_eq!(nfa.shortest_string().as_deref(), Some("ac"));
+        let dfa = Dfa::from_str("ab.a*|").unwrap();
+        assert_eq!(dfa.shortest_string().as_deref(), Some(""));
+        let dfa = Dfa::from_str("ab.")
+            .unwrap()
+            .difference(&Dfa::from_str("ab.").unwrap());
+        assert_eq!(dfa.shortest_string(), None);
+    }
+
+    #[test]
+    fn test_strings() {
+        let nfa = Nfa::from_str("ab|cd|.").unwrap();
+        assert_eq!(nfa.strings().collect::<Vec<_>>(), ["ac", "ad", "bc", "bd"]);
+
+        let nfa = Nfa::from_str("ab|*").unwrap();
+        let strings: Vec<_> = nfa.strings().take(7).collect();
+        assert_eq!(strings, ["", "a", "b", "aa", "ab", "ba", "bb"]);
+
+        // Gaps in lengths and a finite end
+        let nfa = Nfa::from_str("aaa..b?.").unwrap();
+        assert_eq!(nfa.strings().collect::<Vec<_>>(), ["aaa", "aaab"]);
+    }
+
+    #[test]
+    fn test_random_strings() {
+        let dfa = Dfa::from_str(r"\p{Greek}+x?.").unwrap();
+        let strings: Vec<_> = dfa.random_strings(7).take(20).collect();
+        assert_eq!(strings.len(), 20);
+        assert!(strings.iter().all(|s| dfa.test(s)));
+        assert_eq!(strings, dfa.random_strings(7).take(20).collect::<Vec<_>>());
+        assert_ne!(strings, dfa.random_strings(8).take(20).collect::<Vec<_>>());
+
+        let empty = Dfa::from_str("a")
+            .unwrap()
+            .intersection(&Dfa::from_str("b").unwrap());
+        assert_eq!(empty.random_strings(7).next(), None);
+    }
+
+    #[test]
+    fn test_count_strings() {
+        let nfa = Nfa::from_str("ab|*").unwrap();
+        assert_eq!(nfa.count_strings(0), Some(1));
+        assert_eq!(nfa.count_strings(10), Some(1024));
+        let nfa = Nfa::from_str(r"\p{ASCII}\p{Lu}|").unwrap();
+        let lu = CharClass::unicode_property("Lu").unwrap();
+        assert_eq!(
+            nfa.count_strings(1),
+            Some(128 + lu.chars().count() as u128 - 26)
+        );
+        assert_eq!(Nfa::from_str(r"\p{Any}*").unwrap().count_strings(10), None);
+    }
+}
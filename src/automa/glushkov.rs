@@ -0,0 +1,191 @@
+use crate::class::CharClass;
+
+use super::{
+    error::NfaError,
+    nfa::{Draft, Node},
+    Nfa,
+};
+
+/// How [`Builder`](super::Builder) turns a pattern into an automaton.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Construction {
+    /// One fragment per operator, linked by epsilon edges.
+    #[default]
+    Thompson,
+    /// Position automaton without epsilon edges.
+    Glushkov,
+}
+
+/// Positions of a sub-pattern that can start and end its matches.
+struct Fragment {
+    nullable: bool,
+    first: Vec<usize>,
+    last: Vec<usize>,
+}
+
+/// Literal positions and the positions following each.
+struct Positions {
+    classes: Vec<CharClass>,
+    follow: Vec<Vec<usize>>,
+    /// Bound on the positions plus the initial state.
+    max_states: Option<usize>,
+}
+
+impl Positions {
+    /// Fragment of the last complete sub-pattern of `nodes`.
+    fn fragment(&mut self, nodes: Vec<Node>) -> Result<Fragment, NfaError> {
+        let mut stack: Vec<Fragment> = vec![];
+        for node in nodes {
+            let fragment = match node {
+                Node::Char(c) => self.literal(CharClass::from_char(c))?,
+                Node::Class(class) => self.literal(class)?,
+                Node::Catenation => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    self.link(&lhs.last, &rhs.first);
+                    Fragment {
+                        nullable: lhs.nullable && rhs.nullable,
+                        first: union(&lhs.first, rhs.first.iter().filter(|_| lhs.nullable)),
+                        last: union(&rhs.last, lhs.last.iter().filter(|_| rhs.nullable)),
+                    }
+                }
+                Node::Alternation => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    Fragment {
+                        nullable: lhs.nullable || rhs.nullable,
+                        first: union(&lhs.first, &rhs.first),
+                        last: union(&lhs.last, &rhs.last),
+                    }
+                }
+                Node::ZeroOrOne => Fragment {
+                    nullable: true,
+                    ..stack.pop().unwrap()
+                },
+                Node::ZeroOrMore => {
+                    let fragment = stack.pop().unwrap();
+                    self.link(&fragment.last, &fragment.first);
+                    Fragment {
+                        nullable: true,
+                        ..fragment
+                    }
+                }
+                Node::OneOrMore => {
+                    let fragment = stack.pop().unwrap();
+                    self.link(&fragment.last, &fragment.first);
+                    fragment
+                }
+            };
+            stack.push(fragment);
+        }
+        // `Node::parse` has checked that there is a last sub-pattern
+        Ok(stack.pop().unwrap())
+    }
+
+    /// Fails before the follow sets of too many positions are computed.
+    fn literal(&mut self, class: CharClass) -> Result<Fragment, NfaError> {
+        let position = self.classes.len();
+        if let Some(limit) = self.max_states.filter(|&limit| position + 1 >= limit) {
+            return Err(NfaError::TooManyStates(limit));
+        }
+        self.classes.push(class);
+        self.follow.push(vec![]);
+        Ok(Fragment {
+            nullable: false,
+            first: vec![position],
+            last: vec![position],
+        })
+    }
+
+    /// Lets every position of `first` follow every position of `last`.
+    fn link(&mut self, last: &[usize], first: &[usize]) {
+        for &position in last {
+            self.follow[position] = union(&self.follow[position], first);
+        }
+    }
+}
+
+fn union<'a>(a: &'a [usize], b: impl IntoIterator<Item = &'a usize>) -> Vec<usize> {
+    let mut union: Vec<_> = a.iter().chain(b).copied().collect();
+    union.sort_unstable();
+    union.dedup();
+    union
+}
+
+impl Nfa {
+    /// State `p + 1` is reached by reading the literal at position `p`.
+    pub(super) fn glushkov(nodes: Vec<Node>, max_states: Option<usize>) -> Result<Nfa, NfaError> {
+        let mut positions = Positions {
+            classes: vec![],
+            follow: vec![],
+            max_states,
+        };
+        let fragment = positions.fragment(nodes)?;
+        let mut draft = Draft::new(max_states);
+        let initial_state = draft.add_state()?;
+        let states = positions
+            .classes
+            .iter()
+            .map(|_| draft.add_state())
+            .collect::<Result<Vec<_>, _>>()?;
+        for &position in &fragment.first {
+            let class = positions.classes[position].clone();
+            draft.add_edge(initial_state, Some(class), states[position]);
+        }
+        for (position, follow) in positions.follow.iter().enumerate() {
+            for &next in follow {
+                let class = positions.classes[next].clone();
+                draft.add_edge(states[position], Some(class), states[next]);
+            }
+        }
+        let mut accepted_states: Vec<_> = fragment.last.iter().map(|&p| states[p]).collect();
+        if fragment.nullable {
+            accepted_states.push(initial_state);
+        }
+        Ok(draft.finish(initial_state, accepted_states))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Builder;
+
+    use super::*;
+
+    fn glushkov(expr: &str) -> Nfa {
+        Builder::new()
+            .classes(true)
+            .construction(Construction::Glushkov)
+            .build_nfa(expr)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_glushkov() {
+        let nfa = glushkov("ab|*a.b.b.");
+        assert_eq!(nfa.state_count(), 6);
+        assert!(!nfa.has_epsilons());
+        assert!(nfa.test("abb"));
+        assert!(nfa.test("babaabb"));
+        assert!(!nfa.test("abba"));
+
+        let nfa = glushkov("a?b*.");
+        assert!(nfa.test(""));
+        assert!(nfa.test("abb"));
+        assert!(!nfa.test("ba"));
+    }
+
+    #[test]
+    fn test_equivalent_to_thompson() {
+        for expr in [
+            "ab|*a.b.b.",
+            "ab.+c?.",
+            "a*b*|c+.",
+            r"\p{Greek}+[0-9]?.",
+            "a**b+*.",
+        ] {
+            let thompson = Builder::new().classes(true).build_nfa(expr).unwrap();
+            assert!(glushkov(expr).is_equivalent(&thompson).is_yes(), "{expr}");
+        }
+    }
+}
@@ -1,13 +1,30 @@
-use petgraph::{graph::NodeIndex, Graph};
+use petgraph::Graph;
 
+use crate::class::CharClass;
+
+mod bitparallel;
+mod builder;
 mod dfa;
+mod eliminate;
 mod error;
+mod generate;
+mod glushkov;
 mod nfa;
+mod product;
+mod simplify;
+mod thompson;
 
+pub use bitparallel::{BitParallel, MAX_POSITIONS};
+pub use builder::Builder;
 pub use dfa::Dfa;
+pub use generate::{RandomStrings, Strings};
+pub use glushkov::Construction;
 pub use nfa::Nfa;
+pub use product::Answer;
 
-type State = NodeIndex<u32>;
+/// Index of a state of an [`Nfa`].
+type State = u32;
 type NodeLabel = String;
-type EdgeLabel = Option<char>;
-type NfaGraph = Graph<NodeLabel, EdgeLabel>;
+type EdgeLabel = Option<CharClass>;
+/// Graph of an automaton, as given by [`Nfa::to_graph`].
+pub type NfaGraph = Graph<NodeLabel, EdgeLabel>;
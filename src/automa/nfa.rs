@@ -1,308 +1,514 @@
-use petgraph::visit::EdgeRef;
-use petgraph::Graph;
+use std::{collections::HashMap, str::Chars};
 
-use super::{error::NfaError, NfaGraph, State};
+use crate::class::CharClass;
 
+use super::{error::NfaError, Builder, NfaGraph, State};
+
+/// Transitions of state `s` are `transitions[offsets[s]..offsets[s + 1]]`,
+/// epsilon ones first.
 pub struct Nfa {
-    pub(super) graph: NfaGraph,
+    offsets: Vec<u32>,
+    transitions: Vec<Transition>,
+    /// Distinct classes of the transitions.
+    classes: Vec<CharClass>,
+    accepting: Vec<bool>,
     pub(super) initial_state: State,
-    pub(super) accepted_states: Vec<State>,
 }
 
-impl Nfa {
-    fn merge(
-        g1: &NfaGraph,
-        g2: &NfaGraph,
-    ) -> (NfaGraph, impl Fn(State) -> State, impl Fn(State) -> State) {
-        let mut graph = Graph::new();
-        let node_mapping_1: Vec<_> = g1
-            .node_indices()
-            .map(|node_index| {
-                let node_weight = g1.node_weight(node_index).unwrap().clone();
-                graph.add_node(node_weight.clone())
-            })
-            .collect();
+/// Reads `classes[class]`, unless `class` is [`EPSILON`].
+#[derive(Debug, Clone, Copy)]
+struct Transition {
+    class: u32,
+    target: State,
+}
 
-        let node_mapping_2: Vec<_> = g2
-            .node_indices()
-            .map(|node_index| {
-                let node_weight = g2.node_weight(node_index).unwrap().clone();
-                graph.add_node(node_weight.clone())
-            })
-            .collect();
+const EPSILON: u32 = u32::MAX;
+
+/// Automaton under construction.
+#[derive(Default)]
+pub(super) struct Draft {
+    states: u32,
+    edges: Vec<(State, Option<CharClass>, State)>,
+    max_states: Option<usize>,
+}
 
-        let node_mapper_1 = move |node_index: State| node_mapping_1[node_index.index()];
-        let node_mapper_2 = move |node_index: State| node_mapping_2[node_index.index()];
+impl Draft {
+    pub(super) fn new(max_states: Option<usize>) -> Self {
+        Self {
+            max_states,
+            ..Self::default()
+        }
+    }
 
-        for edge in g1.raw_edges() {
-            let source = node_mapper_1(edge.source());
-            let target = node_mapper_1(edge.target());
-            graph.add_edge(source, target, edge.weight.clone());
+    /// Draft of `n` states and no edges yet.
+    fn with_states(n: usize) -> Self {
+        Self {
+            states: n as State,
+            ..Self::default()
         }
+    }
 
-        for edge in g2.raw_edges() {
-            let source = node_mapper_2(edge.source());
-            let target = node_mapper_2(edge.target());
-            graph.add_edge(source, target, edge.weight.clone());
+    /// Fails as soon as the draft would exceed its `max_states`.
+    pub(super) fn add_state(&mut self) -> Result<State, NfaError> {
+        if let Some(limit) = self
+            .max_states
+            .filter(|&limit| self.states as usize >= limit)
+        {
+            return Err(NfaError::TooManyStates(limit));
         }
+        self.states += 1;
+        Ok(self.states - 1)
+    }
+
+    /// Adds an edge reading a char of `class`, or an epsilon edge.
+    pub(super) fn add_edge(&mut self, source: State, class: Option<CharClass>, target: State) {
+        self.edges.push((source, class, target));
+    }
 
-        (graph, Box::new(node_mapper_1), Box::new(node_mapper_2))
+    pub(super) fn finish(
+        self,
+        initial_state: State,
+        accepted_states: impl IntoIterator<Item = State>,
+    ) -> Nfa {
+        let n = self.states as usize;
+        let mut accepting = vec![false; n];
+        for state in accepted_states {
+            accepting[state as usize] = true;
+        }
+        // Counting sort by source, epsilon edges first
+        let mut offsets = vec![0u32; n + 1];
+        for (source, _, _) in &self.edges {
+            offsets[*source as usize + 1] += 1;
+        }
+        for i in 0..n {
+            offsets[i + 1] += offsets[i];
+        }
+        let mut epsilons = offsets.clone();
+        let mut others = offsets.clone();
+        for (source, class, _) in &self.edges {
+            if class.is_none() {
+                others[*source as usize] += 1;
+            }
+        }
+        let mut classes = vec![];
+        let mut ids: HashMap<CharClass, u32> = HashMap::new();
+        let mut transitions = vec![
+            Transition {
+                class: EPSILON,
+                target: 0
+            };
+            self.edges.len()
+        ];
+        for (source, class, target) in self.edges {
+            let (next, class) = match class {
+                None => (&mut epsilons[source as usize], EPSILON),
+                Some(class) => {
+                    let id = *ids.entry(class).or_insert_with_key(|class| {
+                        classes.push(class.clone());
+                        classes.len() as u32 - 1
+                    });
+                    (&mut others[source as usize], id)
+                }
+            };
+            transitions[*next as usize] = Transition { class, target };
+            *next += 1;
+        }
+        Nfa {
+            offsets,
+            transitions,
+            classes,
+            accepting,
+            initial_state,
+        }
     }
 }
 
 impl Nfa {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(expr: &str) -> Result<Nfa, NfaError> {
+        Builder::new().build_nfa(expr)
+    }
+}
+
+/// Symbol of a postfix pattern, operators following their operands.
+pub(super) enum Node {
+    Char(char),
+    Class(CharClass),
+    Catenation,
+    Alternation,
+    ZeroOrOne,
+    ZeroOrMore,
+    OneOrMore,
+}
+
+impl Node {
+    /// Parses a postfix pattern where `.` is catenation, `|` alternation and
+    /// `?*+` the usual repetitions. `\` escapes the next char, `\p{..}`
+    /// names a Unicode property and, with `Builder::classes`, `[..]` is a
+    /// class.
+    /// The symbols are checked to have their operands, and the automaton is
+    /// built from the last complete sub-pattern.
+    pub(super) fn parse(expr: &str, builder: &Builder) -> Result<Vec<Node>, NfaError> {
         let parse_error = || NfaError::InvalidRegex(expr.to_string());
-        let mut stack: Vec<Nfa> = Vec::new();
-        for c in expr.chars() {
+        let mut nodes: Vec<Node> = Vec::new();
+        // Number of complete sub-patterns so far
+        let mut depth = 0usize;
+        let mut chars = expr.chars();
+        let fold = |class: CharClass| {
+            if builder.case_insensitive {
+                class.case_fold()
+            } else {
+                class
+            }
+        };
+        while let Some(c) = chars.next() {
             match c {
-                '.' => {
-                    let rhs = stack.pop().ok_or_else(parse_error)?;
-                    let lhs = stack.pop().ok_or_else(parse_error)?;
-                    stack.push(lhs.catenation(rhs));
+                '.' | '|' => {
+                    depth = depth
+                        .checked_sub(1)
+                        .filter(|&d| d > 0)
+                        .ok_or_else(parse_error)?;
+                    nodes.push(match c {
+                        '.' => Node::Catenation,
+                        _ => Node::Alternation,
+                    });
+                    continue;
                 }
-                '|' => {
-                    let rhs = stack.pop().ok_or_else(parse_error)?;
-                    let lhs = stack.pop().ok_or_else(parse_error)?;
-                    stack.push(lhs.alternation(rhs));
-                }
-                '?' => {
-                    let nfa = stack.pop().ok_or_else(parse_error)?;
-                    stack.push(nfa.zero_or_one());
+                '?' | '*' | '+' => {
+                    if depth == 0 {
+                        return Err(parse_error());
+                    }
+                    nodes.push(match c {
+                        '?' => Node::ZeroOrOne,
+                        '*' => Node::ZeroOrMore,
+                        _ => Node::OneOrMore,
+                    });
+                    continue;
                 }
-                '*' => {
-                    let nfa = stack.pop().ok_or_else(parse_error)?;
-                    stack.push(nfa.zero_or_more());
+                '\\' => {
+                    let class = match chars.next().ok_or_else(parse_error)? {
+                        c @ ('p' | 'P') => {
+                            let name: String = match chars.next().ok_or_else(parse_error)? {
+                                '{' => {
+                                    let mut name = String::new();
+                                    loop {
+                                        match chars.next().ok_or_else(parse_error)? {
+                                            '}' => break name,
+                                            c => name.push(c),
+                                        }
+                                    }
+                                }
+                                c => c.to_string(),
+                            };
+                            let class = CharClass::unicode_property(&name)
+                                .ok_or(NfaError::UnknownProperty(name))?;
+                            if c == 'P' {
+                                class.negate()
+                            } else {
+                                class
+                            }
+                        }
+                        c => CharClass::from_char(c),
+                    };
+                    nodes.push(Node::Class(fold(class)));
                 }
-                '+' => {
-                    let nfa = stack.pop().ok_or_else(parse_error)?;
-                    stack.push(nfa.one_or_more());
+                '[' if builder.classes => {
+                    let class = parse_bracket(&mut chars).ok_or_else(parse_error)?;
+                    nodes.push(Node::Class(fold(class)));
                 }
-                _ => {
-                    stack.push(Nfa::literal_character(c));
+                _ if builder.case_insensitive => {
+                    nodes.push(Node::Class(fold(CharClass::from_char(c))));
                 }
+                _ => nodes.push(Node::Char(c)),
             }
+            depth += 1;
+        }
+        if depth == 0 {
+            return Err(parse_error());
         }
-        stack.pop().ok_or_else(parse_error)
+        Ok(nodes)
     }
+}
 
-    fn literal_character(c: char) -> Self {
-        let mut graph = NfaGraph::new();
-        let initial_state = graph.add_node("".to_string());
-        let node = graph.add_node("".to_string());
-        graph.add_edge(initial_state, node, Some(c));
-        Self {
-            graph,
-            initial_state,
-            accepted_states: vec![node],
+/// Parses the rest of a class after its `[`, where `\` escapes the next char.
+fn parse_bracket(chars: &mut Chars) -> Option<CharClass> {
+    let mut chars = chars.peekable();
+    let negate = chars.next_if_eq(&'^').is_some();
+    let mut ranges = vec![];
+    loop {
+        let start = match chars.next()? {
+            ']' => break,
+            '\\' => chars.next()?,
+            c => c,
+        };
+        if chars.next_if_eq(&'-').is_none() {
+            ranges.push((start, start));
+            continue;
+        }
+        let end = match chars.next()? {
+            ']' => {
+                ranges.extend([(start, start), ('-', '-')]);
+                break;
+            }
+            '\\' => chars.next()?,
+            c => c,
+        };
+        if end < start {
+            return None;
         }
+        ranges.push((start, end));
     }
+    let class = CharClass::new(ranges);
+    Some(if negate { class.negate() } else { class })
+}
 
-    fn catenation(self, rhs: Nfa) -> Self {
-        let (mut graph, mapper1, mapper2) = Self::merge(&self.graph, &rhs.graph);
-        for sink in self.accepted_states {
-            graph.add_edge(mapper1(sink), mapper2(rhs.initial_state), None);
+impl Nfa {
+    /// Deterministic automaton from the transitions of each state.
+    pub(crate) fn from_transitions(
+        transitions: Vec<Vec<(CharClass, usize)>>,
+        accepting: &[bool],
+    ) -> Self {
+        let mut draft = Draft::with_states(accepting.len());
+        for (i, row) in transitions.into_iter().enumerate() {
+            let mut targets: Vec<(usize, CharClass)> = vec![];
+            for (class, target) in row {
+                match targets.iter_mut().find(|(target1, _)| *target1 == target) {
+                    Some((_, class1)) => *class1 = class1.union(&class),
+                    None => targets.push((target, class)),
+                }
+            }
+            for (target, class) in targets {
+                draft.add_edge(i as State, Some(class), target as State);
+            }
         }
-        Self {
-            graph,
-            initial_state: mapper1(self.initial_state),
-            accepted_states: rhs.accepted_states.iter().map(|&s| mapper2(s)).collect(),
+        let accepted_states = (0..accepting.len())
+            .filter(|&i| accepting[i])
+            .map(|i| i as State);
+        draft.finish(0, accepted_states)
+    }
+
+    /// Automaton accepting the mirror image of every accepted string.
+    pub fn reverse(&self) -> Nfa {
+        let initial = self.state_count() as State;
+        let mut draft = Draft::with_states(self.state_count() + 1);
+        for source in 0..self.state_count() as State {
+            for (class, target) in self.edges(source) {
+                draft.add_edge(target, class.cloned(), source);
+            }
+        }
+        for state in self.accepted_states() {
+            draft.add_edge(initial, None, state);
         }
+        draft.finish(initial, [self.initial_state])
+    }
+}
+
+impl Nfa {
+    pub fn state_count(&self) -> usize {
+        self.accepting.len()
+    }
+
+    pub(super) fn is_accepting(&self, state: State) -> bool {
+        self.accepting[state as usize]
     }
 
-    fn alternation(self, rhs: Nfa) -> Self {
-        let (mut graph, mapper1, mapper2) = Self::merge(&self.graph, &rhs.graph);
-        let initial_state = graph.add_node("".to_string());
-        graph.add_edge(initial_state, mapper1(self.initial_state), None);
-        graph.add_edge(initial_state, mapper2(rhs.initial_state), None);
+    pub(super) fn accepted_states(&self) -> impl Iterator<Item = State> + '_ {
+        (0..self.state_count() as State).filter(|&state| self.is_accepting(state))
+    }
+
+    fn transitions(&self, state: State) -> &[Transition] {
+        let state = state as usize;
+        &self.transitions[self.offsets[state] as usize..self.offsets[state + 1] as usize]
+    }
 
-        let accepted_states = self
-            .accepted_states
+    /// `None` class for epsilon edges.
+    pub(super) fn edges(
+        &self,
+        state: State,
+    ) -> impl Iterator<Item = (Option<&CharClass>, State)> + '_ {
+        self.transitions(state)
             .iter()
-            .map(|&s| mapper1(s))
-            .chain(rhs.accepted_states.iter().map(|&s| mapper2(s)))
-            .collect();
+            .map(|t| (self.classes.get(t.class as usize), t.target))
+    }
 
-        Self {
-            graph,
-            initial_state,
-            accepted_states,
-        }
+    /// Outgoing edges of `state` that read a char.
+    pub(super) fn class_edges(
+        &self,
+        state: State,
+    ) -> impl Iterator<Item = (&CharClass, State)> + '_ {
+        self.transitions(state)
+            .iter()
+            .filter(|t| t.class != EPSILON)
+            .map(|t| (&self.classes[t.class as usize], t.target))
     }
 
-    fn zero_or_one(self) -> Self {
-        let mut graph = self.graph.clone();
-        let initial_state = graph.add_node("".to_string());
-        graph.add_edge(initial_state, self.initial_state, None);
-        let accepted_states = [vec![initial_state], self.accepted_states].concat();
-        Self {
-            graph,
-            initial_state,
-            accepted_states,
-        }
+    /// Targets of the epsilon edges of `state`.
+    fn epsilons(&self, state: State) -> impl Iterator<Item = State> + '_ {
+        self.transitions(state)
+            .iter()
+            .take_while(|t| t.class == EPSILON)
+            .map(|t| t.target)
     }
 
-    fn zero_or_more(self) -> Self {
-        let mut graph = self.graph.clone();
-        let initial_state = graph.add_node("".to_string());
-        graph.add_edge(initial_state, self.initial_state, None);
-        for sink in self.accepted_states {
-            graph.add_edge(sink, self.initial_state, None);
-        }
-        let accepted_states = vec![initial_state];
-        Self {
-            graph,
-            initial_state,
-            accepted_states,
-        }
+    /// Distinct classes read by the edges.
+    pub(super) fn classes(&self) -> &[CharClass] {
+        &self.classes
     }
 
-    fn one_or_more(self) -> Self {
-        let mut graph = self.graph.clone();
-        let new_state = graph.add_node("".to_string());
-        for sink in self.accepted_states {
-            graph.add_edge(sink, new_state, None);
-        }
-        graph.add_edge(new_state, self.initial_state, None);
-        Self {
-            graph,
-            initial_state: self.initial_state,
-            accepted_states: vec![new_state],
-        }
+    /// Whether some edge is an epsilon one.
+    pub fn has_epsilons(&self) -> bool {
+        self.transitions.iter().any(|t| t.class == EPSILON)
     }
-}
 
-impl Nfa {
-    pub(super) fn get_next_states(&self, cur: State) -> Vec<State> {
-        let mut stack = vec![cur];
-        let mut next_states = vec![];
+    pub fn transition_count(&self) -> usize {
+        self.transitions.len()
+    }
+
+    /// Epsilon closure of `state`, added to `states` unless already marked.
+    fn add_closure(&self, state: State, marks: &mut [bool], states: &mut Vec<State>) {
+        let mut stack = vec![state];
         while let Some(state) = stack.pop() {
-            let mut has_weighted_edge = false;
-            if self.graph.edges(state).count() == 0 {
-                // Accept state
-                next_states.push(state);
+            if std::mem::replace(&mut marks[state as usize], true) {
                 continue;
             }
-            for edge in self.graph.edges(state) {
-                if edge.weight().is_none() {
-                    stack.push(edge.target());
-                } else {
-                    has_weighted_edge = true;
-                }
-            }
-            if has_weighted_edge {
-                next_states.push(state)
-            }
+            states.push(state);
+            stack.extend(self.epsilons(state));
         }
-        next_states
+    }
+}
+
+impl Nfa {
+    /// Epsilon closure of `cur`, keeping states that consume a char or accept.
+    pub(super) fn get_next_states(&self, cur: State) -> Vec<State> {
+        let mut closure = vec![];
+        self.add_closure(cur, &mut vec![false; self.state_count()], &mut closure);
+        closure.retain(|&state| {
+            self.is_accepting(state)
+                || self
+                    .transitions(state)
+                    .last()
+                    .is_some_and(|t| t.class != EPSILON)
+        });
+        closure
     }
 
     pub fn test(&self, str: &str) -> bool {
-        let mut current_states = self.get_next_states(self.initial_state);
+        let mut marks = vec![false; self.state_count()];
+        let mut current_states = vec![];
+        self.add_closure(self.initial_state, &mut marks, &mut current_states);
+        let mut next_states = vec![];
         for c in str.chars() {
-            let mut next_states = Vec::new();
-            for state in current_states {
-                for edge in self.graph.edges(state) {
-                    if let Some(c_) = edge.weight() {
-                        if c == *c_ {
-                            next_states.extend(self.get_next_states(edge.target()));
-                        }
+            for &state in &current_states {
+                marks[state as usize] = false;
+            }
+            for &state in &current_states {
+                for (class, target) in self.class_edges(state) {
+                    if class.contains(c) {
+                        self.add_closure(target, &mut marks, &mut next_states);
                     }
                 }
             }
-            current_states = next_states;
+            std::mem::swap(&mut current_states, &mut next_states);
+            next_states.clear();
         }
-        current_states
-            .iter()
-            .any(|&s| self.accepted_states.contains(&s))
+        current_states.iter().any(|&s| self.is_accepting(s))
     }
 }
 
 impl Nfa {
+    /// Graph of the automaton for visualization, with empty node labels.
+    pub fn to_graph(&self) -> NfaGraph {
+        let mut graph = NfaGraph::new();
+        let nodes: Vec<_> = (0..self.state_count())
+            .map(|_| graph.add_node("".to_string()))
+            .collect();
+        for state in 0..self.state_count() as State {
+            for (class, target) in self.edges(state) {
+                graph.add_edge(
+                    nodes[state as usize],
+                    nodes[target as usize],
+                    class.cloned(),
+                );
+            }
+        }
+        graph
+    }
+
     pub fn to_json(&self) -> String {
-        serde_json::to_string(&self.graph).unwrap()
+        serde_json::to_string(&self.to_graph()).unwrap()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-
-    #[test]
-    fn test_literal_charater() {
-        let nfa = Nfa::literal_character('a');
-        assert_eq!(nfa.graph.node_count(), 2);
-        assert_eq!(nfa.graph.edge_count(), 1);
-        assert_eq!(
-            nfa.graph.edges(nfa.initial_state).next().unwrap().weight(),
-            &Some('a')
-        );
-    }
+    use super::super::Construction;
 
-    #[test]
-    fn test_catenation() {
-        let nfa_1 = Nfa::literal_character('a');
-        let nfa_2 = Nfa::literal_character('b');
-        let nfa = nfa_1.catenation(nfa_2);
-        assert_eq!(nfa.graph.node_count(), 4);
-        assert_eq!(nfa.graph.edge_count(), 3);
-        assert!(nfa
-            .graph
-            .edge_weights()
-            .find(|e| e.filter(|&w| w == 'a').is_some())
-            .is_some());
-        assert!(nfa
-            .graph
-            .edge_weights()
-            .find(|e| e.filter(|&w| w == 'b').is_some())
-            .is_some());
-    }
+    use super::*;
 
     #[test]
-    fn test_alternation() {
-        let nfa_1 = Nfa::literal_character('a');
-        let nfa_2 = Nfa::literal_character('b');
-        let nfa = nfa_1.alternation(nfa_2);
-        assert_eq!(nfa.graph.node_count(), 5);
-        assert_eq!(nfa.graph.edge_count(), 4);
+    fn test_to_graph() {
+        let nfa = Nfa::from_str("ab|*a.").unwrap();
+        let graph = nfa.to_graph();
+        assert_eq!(graph.node_count(), nfa.state_count());
+        assert_eq!(graph.edge_count(), nfa.transition_count());
+        assert_eq!(graph.edge_weights().filter(|w| w.is_none()).count(), 6);
+        assert_eq!(nfa.classes().len(), 2);
+        assert_eq!(std::mem::size_of::<Transition>(), 8);
     }
 
     #[test]
-    fn test_zero_or_one() {
-        let nfa = Nfa::literal_character('a').zero_or_one();
-        assert_eq!(nfa.graph.node_count(), 3);
-        assert_eq!(nfa.graph.edge_count(), 2);
+    fn test_from_str() {
+        let nfa = Nfa::from_str("abb...");
+        assert!(nfa.is_err());
+        let nfa = Nfa::from_str("abb.+.a.");
+        assert!(nfa.is_ok());
     }
 
     #[test]
-    fn test_zero_or_more() {
-        let nfa = Nfa::literal_character('a').zero_or_more();
-        assert_eq!(nfa.graph.node_count(), 3);
-        assert_eq!(nfa.graph.edge_count(), 3);
+    fn test_case_insensitive() {
+        let nfa = Builder::new()
+            .case_insensitive(true)
+            .build_nfa("ok.σ.")
+            .unwrap();
+        assert!(nfa.test("OKΣ"));
+        assert!(nfa.test("o\u{212A}ς"));
+        assert!(!nfa.test("oks"));
     }
 
     #[test]
-    fn test_one_or_more() {
-        let nfa = Nfa::literal_character('a').one_or_more();
-        assert_eq!(nfa.graph.node_count(), 3);
-        assert_eq!(nfa.graph.edge_count(), 3);
+    fn test_property() {
+        let nfa = Nfa::from_str(r"\p{Greek}+\PL.\..").unwrap();
+        assert_eq!(nfa.transition_count(), 7);
+        assert!(nfa.test("λόγος1."));
+        assert!(!nfa.test("logos1."));
+        assert!(!nfa.test("λόγοςa."));
+        assert!(!nfa.test("λόγος1x"));
+        assert!(matches!(
+            Nfa::from_str(r"\p{Klingon}"),
+            Err(NfaError::UnknownProperty(name)) if name == "Klingon"
+        ));
+        assert!(matches!(
+            Nfa::from_str(r"\p{Greek"),
+            Err(NfaError::InvalidRegex(_))
+        ));
     }
 
     #[test]
-    fn test_merge() {
-        let g1 = Nfa::literal_character('a').graph;
-        let g2 = Nfa::literal_character('b').graph;
-        let (graph, _, _) = Nfa::merge(&g1, &g2);
-        assert_eq!(graph.node_count(), 4);
-        assert_eq!(graph.edge_count(), 2);
-    }
+    fn test_bracket() {
+        let builder = Builder::new().classes(true).clone();
+        let nfa = builder.build_nfa(r"[a-c\]][^-].").unwrap();
+        assert_eq!(nfa.transition_count(), 3);
+        assert!(nfa.test("]x"));
+        assert!(nfa.test("bx"));
+        assert!(!nfa.test("b-"));
+        assert!(!nfa.test("dx"));
+        assert!(builder.build_nfa("[z-a]").is_err());
+        assert!(builder.build_nfa("[ab").is_err());
 
-    #[test]
-    fn test_from_str() {
-        let nfa = Nfa::from_str("abb...");
-        assert!(nfa.is_err());
-        let nfa = Nfa::from_str("abb.+.a.");
-        assert!(nfa.is_ok());
+        // Plain chars by default
+        let nfa = Nfa::from_str("[a.b.").unwrap();
+        assert!(nfa.test("[ab"));
+        assert!(Nfa::from_str("[ab").is_ok());
     }
 
     #[test]
@@ -316,5 +522,45 @@ mod tests {
         let nfa = Nfa::from_str("abab...abbb...|").unwrap();
         assert!(nfa.test("abab"));
         assert!(nfa.test("abbb"));
+
+        // Accepting states with only epsilon edges
+        let nfa = Nfa::from_str("ab+.a**.").unwrap();
+        assert!(nfa.test("ab"));
+        assert!(nfa.test("abbaa"));
+        assert!(!nfa.test("a"));
+    }
+
+    #[test]
+    fn test_reverse() {
+        let nfa = Nfa::from_str("ab.c+.").unwrap().reverse();
+        assert!(nfa.test("cba"));
+        assert!(nfa.test("cccba"));
+        assert!(!nfa.test("abc"));
+        assert!(!nfa.test("ba"));
+
+        let nfa = Nfa::from_str("ab.*").unwrap().reverse();
+        assert!(nfa.test(""));
+        assert!(nfa.test("baba"));
+        assert!(!nfa.test("abab"));
+    }
+
+    #[test]
+    fn test_max_states() {
+        let mut builder = Builder::new();
+        builder.max_states(8);
+        assert!(builder.build_nfa("ab.").is_ok());
+        assert!(matches!(
+            builder.build_nfa("ab.c.d.e."),
+            Err(NfaError::TooManyStates(8))
+        ));
+        // Stops at the first state past the limit
+        let expr = format!("a{}", "b.".repeat(100_000));
+        builder.max_states(100);
+        for construction in [Construction::Thompson, Construction::Glushkov] {
+            assert!(matches!(
+                builder.construction(construction).build_nfa(&expr),
+                Err(NfaError::TooManyStates(100))
+            ));
+        }
     }
 }
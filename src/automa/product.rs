@@ -0,0 +1,306 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::class::CharClass;
+
+use super::{Dfa, Nfa, State};
+
+impl Dfa {
+    /// Accepts the strings accepted by both automata.
+    pub fn intersection(&self, other: &Dfa) -> Dfa {
+        self.product(other, |a, b| a && b)
+    }
+
+    /// Accepts the strings accepted by either automaton.
+    pub fn union(&self, other: &Dfa) -> Dfa {
+        self.product(other, |a, b| a || b)
+    }
+
+    /// Accepts the strings accepted by `self` but not by `other`.
+    pub fn difference(&self, other: &Dfa) -> Dfa {
+        self.product(other, |a, b| a && !b)
+    }
+
+    /// Accepts the strings made of chars of `alphabet` that `self` rejects.
+    pub fn complement(&self, alphabet: &CharClass) -> Dfa {
+        let classes = edge_classes(&self.nfa).chain([alphabet]);
+        let alphabet = CharClass::partition(classes)
+            .into_iter()
+            .filter(|piece| alphabet.contains(piece.ranges()[0].0))
+            .collect();
+        Dfa::new(Product::new(&[&self.nfa], alphabet).build(|accepted| !accepted[0]))
+    }
+
+    fn product(&self, other: &Dfa, accept: impl Fn(bool, bool) -> bool) -> Dfa {
+        let product = Product::pair(&self.nfa, &other.nfa);
+        Dfa::new(product.build(|accepted| accept(accepted[0], accepted[1])))
+    }
+
+    /// Whether no string is accepted, otherwise one that is.
+    pub fn is_empty(&self) -> Answer {
+        self.nfa.is_empty()
+    }
+
+    /// Otherwise a string accepted by only one of them.
+    pub fn is_equivalent(&self, other: &Dfa) -> Answer {
+        self.nfa.is_equivalent(&other.nfa)
+    }
+
+    /// Otherwise a string `other` rejects.
+    pub fn is_subset_of(&self, other: &Dfa) -> Answer {
+        self.nfa.is_subset_of(&other.nfa)
+    }
+}
+
+impl Nfa {
+    /// Whether no string is accepted, otherwise one that is.
+    pub fn is_empty(&self) -> Answer {
+        let product = Product::new(&[self], CharClass::partition(edge_classes(self)));
+        product.find(|accepted| accepted[0]).into()
+    }
+
+    /// Otherwise a string accepted by only one of them.
+    pub fn is_equivalent(&self, other: &Nfa) -> Answer {
+        let product = Product::pair(self, other);
+        product.find(|accepted| accepted[0] != accepted[1]).into()
+    }
+
+    /// Otherwise a string `other` rejects.
+    pub fn is_subset_of(&self, other: &Nfa) -> Answer {
+        let product = Product::pair(self, other);
+        product.find(|accepted| accepted[0] && !accepted[1]).into()
+    }
+}
+
+/// Outcome of a language check, with a shortest counterexample when negative.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+    Yes,
+    No(String),
+}
+
+impl Answer {
+    pub fn is_yes(&self) -> bool {
+        matches!(self, Answer::Yes)
+    }
+
+    pub fn counterexample(&self) -> Option<&str> {
+        match self {
+            Answer::Yes => None,
+            Answer::No(counterexample) => Some(counterexample),
+        }
+    }
+}
+
+impl From<Option<String>> for Answer {
+    fn from(counterexample: Option<String>) -> Self {
+        counterexample.map_or(Answer::Yes, Answer::No)
+    }
+}
+
+/// Subset construction run on several automata in lockstep, over a
+/// partition of the chars that edge classes never split.
+pub(super) struct Product<'a> {
+    nfas: Vec<&'a Nfa>,
+    alphabet: Vec<CharClass>,
+}
+
+/// One set of NFA states per automaton.
+pub(super) type ProductState = Vec<Vec<State>>;
+
+impl<'a> Product<'a> {
+    pub(super) fn new(nfas: &[&'a Nfa], alphabet: Vec<CharClass>) -> Self {
+        Self {
+            nfas: nfas.to_vec(),
+            alphabet,
+        }
+    }
+
+    /// Product of two automata over the chars of both.
+    pub(super) fn pair(a: &'a Nfa, b: &'a Nfa) -> Self {
+        Self::new(
+            &[a, b],
+            CharClass::partition(edge_classes(a).chain(edge_classes(b))),
+        )
+    }
+
+    pub(super) fn alphabet(&self) -> &[CharClass] {
+        &self.alphabet
+    }
+
+    pub(super) fn initial(&self) -> ProductState {
+        self.nfas
+            .iter()
+            .map(|nfa| closure(nfa, [nfa.initial_state]))
+            .collect()
+    }
+
+    /// Successor of `state` on the alphabet piece at `symbol`.
+    pub(super) fn step(&self, state: &ProductState, symbol: usize) -> ProductState {
+        let c = self.alphabet[symbol].ranges()[0].0;
+        self.nfas
+            .iter()
+            .zip(state)
+            .map(|(nfa, states)| {
+                let targets = states.iter().flat_map(|&state| {
+                    nfa.class_edges(state)
+                        .filter(move |(class, _)| class.contains(c))
+                        .map(|(_, target)| target)
+                });
+                closure(nfa, targets)
+            })
+            .collect()
+    }
+
+    /// Whether each automaton accepts in `state`.
+    pub(super) fn accepted(&self, state: &ProductState) -> Vec<bool> {
+        self.nfas
+            .iter()
+            .zip(state)
+            .map(|(nfa, states)| states.iter().any(|&s| nfa.is_accepting(s)))
+            .collect()
+    }
+
+    /// Breadth first, with the transitions of each state per piece.
+    pub(super) fn explore(&self) -> (Vec<ProductState>, Vec<Vec<usize>>) {
+        let initial = self.initial();
+        let mut index = HashMap::from([(initial.clone(), 0)]);
+        let mut states = vec![initial];
+        let mut transitions = vec![];
+        let mut queue = VecDeque::from([0]);
+        while let Some(i) = queue.pop_front() {
+            let row = (0..self.alphabet.len())
+                .map(|symbol| {
+                    let next = self.step(&states[i], symbol);
+                    *index.entry(next.clone()).or_insert_with(|| {
+                        states.push(next);
+                        queue.push_back(states.len() - 1);
+                        states.len() - 1
+                    })
+                })
+                .collect();
+            transitions.push(row);
+        }
+        (states, transitions)
+    }
+
+    /// Shortest string reaching a state where `predicate` holds.
+    pub(super) fn find(&self, predicate: impl Fn(&[bool]) -> bool) -> Option<String> {
+        let initial = self.initial();
+        let mut parents: HashMap<ProductState, Option<(ProductState, char)>> =
+            HashMap::from([(initial.clone(), None)]);
+        let mut queue = VecDeque::from([initial]);
+        while let Some(state) = queue.pop_front() {
+            if predicate(&self.accepted(&state)) {
+                let mut chars = vec![];
+                let mut state = &state;
+                while let Some((parent, c)) = &parents[state] {
+                    chars.push(*c);
+                    state = parent;
+                }
+                return Some(chars.into_iter().rev().collect());
+            }
+            for symbol in 0..self.alphabet.len() {
+                let next = self.step(&state, symbol);
+                if !parents.contains_key(&next) {
+                    let c = self.alphabet[symbol].ranges()[0].0;
+                    parents.insert(next.clone(), Some((state.clone(), c)));
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Builds the deterministic automaton accepting where `accept` holds.
+    pub(super) fn build(&self, accept: impl Fn(&[bool]) -> bool) -> Nfa {
+        let (states, transitions) = self.explore();
+        let transitions = transitions
+            .into_iter()
+            .map(|row| self.alphabet.iter().cloned().zip(row).collect())
+            .collect();
+        let accepting: Vec<_> = states
+            .iter()
+            .map(|state| accept(&self.accepted(state)))
+            .collect();
+        Nfa::from_transitions(transitions, &accepting)
+    }
+}
+
+/// Sorted epsilon closure, keeping states that consume a char or accept.
+fn closure(nfa: &Nfa, states: impl IntoIterator<Item = State>) -> Vec<State> {
+    let mut closure: Vec<_> = states
+        .into_iter()
+        .flat_map(|state| nfa.get_next_states(state))
+        .collect();
+    closure.sort_unstable();
+    closure.dedup();
+    closure
+}
+
+pub(super) fn edge_classes(nfa: &Nfa) -> impl Iterator<Item = &CharClass> {
+    nfa.classes().iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_operations() {
+        let a = Dfa::from_str("ab|*").unwrap();
+        let b = Dfa::from_str("ab.*").unwrap();
+
+        let intersection = a.intersection(&b);
+        assert!(intersection.test("abab"));
+        assert!(intersection.test(""));
+        assert!(!intersection.test("aab"));
+
+        let difference = a.difference(&b);
+        assert!(difference.test("aab"));
+        assert!(!difference.test("abab"));
+        assert!(!difference.test("abc"));
+
+        let union = b.union(&Dfa::from_str("c+").unwrap());
+        assert!(union.test("ab"));
+        assert!(union.test("ccc"));
+        assert!(!union.test("abc"));
+    }
+
+    #[test]
+    fn test_decisions() {
+        let a = Dfa::from_str("ab|*").unwrap();
+        let b = Dfa::from_str("ab.*").unwrap();
+        assert!(b.is_subset_of(&a).is_yes());
+        assert_eq!(a.is_subset_of(&b), Answer::No("a".to_string()));
+        assert_eq!(a.is_equivalent(&b).counterexample(), Some("a"));
+        assert!(a.is_equivalent(&Dfa::from_str("a*b*.*").unwrap()).is_yes());
+
+        assert_eq!(b.is_empty(), Answer::No("".to_string()));
+        assert!(a
+            .intersection(&Dfa::from_str("c").unwrap())
+            .is_empty()
+            .is_yes());
+        assert_eq!(
+            a.difference(&b).is_empty().counterexample().map(str::len),
+            Some(1)
+        );
+
+        let nfa = Nfa::from_str("ab+.").unwrap();
+        assert!(nfa
+            .is_equivalent(&Nfa::from_str("ab.b*.").unwrap())
+            .is_yes());
+        assert_eq!(nfa.is_empty().counterexample(), Some("ab"));
+    }
+
+    #[test]
+    fn test_complement() {
+        let dfa = Dfa::from_str("ab.").unwrap();
+        let complement = dfa.complement(&CharClass::new([('a', 'c')]));
+        assert!(!complement.test("ab"));
+        assert!(complement.test(""));
+        assert!(complement.test("abc"));
+        assert!(complement.test("cc"));
+        assert!(!complement.test("ad"));
+        assert!(complement.complement(&CharClass::any()).test("ab"));
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::class::CharClass;
+
+use super::{Dfa, Nfa, State};
+
+impl Nfa {
+    /// Drops epsilon edges and useless states, and merges identical states.
+    pub fn simplify(&self) -> Nfa {
+        let mut table = Table::without_epsilons(self);
+        table.trim();
+        while table.merge_identical() {}
+        table.into_nfa()
+    }
+}
+
+impl Dfa {
+    /// Same as [`Nfa::simplify`], keeping the result deterministic.
+    pub fn simplify(&self) -> Dfa {
+        Dfa::new(self.nfa.simplify())
+    }
+}
+
+/// Transitions per state, without epsilon edges.
+struct Table {
+    transitions: Vec<Vec<(CharClass, usize)>>,
+    accepting: Vec<bool>,
+    initial: usize,
+}
+
+impl Table {
+    /// Gives each state the transitions and acceptance of its epsilon closure.
+    fn without_epsilons(nfa: &Nfa) -> Self {
+        let n = nfa.state_count();
+        let mut transitions = vec![vec![]; n];
+        let mut accepting = vec![false; n];
+        for state in 0..n {
+            let mut stack = vec![state as State];
+            let mut closure = HashSet::from([state as State]);
+            while let Some(state1) = stack.pop() {
+                accepting[state] |= nfa.is_accepting(state1);
+                for (class, target) in nfa.edges(state1) {
+                    match class {
+                        Some(class) => transitions[state].push((class.clone(), target as usize)),
+                        None if closure.insert(target) => stack.push(target),
+                        None => {}
+                    }
+                }
+            }
+        }
+        Self {
+            transitions,
+            accepting,
+            initial: nfa.initial_state as usize,
+        }
+    }
+
+    /// Drops the unreachable and dead states but the initial one.
+    fn trim(&mut self) {
+        let n = self.accepting.len();
+        let mut reachable = vec![false; n];
+        let mut stack = vec![self.initial];
+        reachable[self.initial] = true;
+        while let Some(state) = stack.pop() {
+            for &(_, target) in &self.transitions[state] {
+                if !std::mem::replace(&mut reachable[target], true) {
+                    stack.push(target);
+                }
+            }
+        }
+
+        let mut live = self.accepting.clone();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for state in 0..n {
+                if !live[state] && self.transitions[state].iter().any(|&(_, t)| live[t]) {
+                    live[state] = true;
+                    changed = true;
+                }
+            }
+        }
+
+        let keep: Vec<_> = (0..n)
+            .map(|state| state == self.initial || (reachable[state] && live[state]))
+            .collect();
+        for transitions in &mut self.transitions {
+            transitions.retain(|&(_, target)| keep[target]);
+        }
+        self.retain(&keep);
+    }
+
+    /// Returns whether any states were merged.
+    fn merge_identical(&mut self) -> bool {
+        let n = self.accepting.len();
+        let mut representatives: HashMap<(bool, BTreeMap<usize, CharClass>), usize> =
+            HashMap::new();
+        let mut mapping: Vec<usize> = (0..n).collect();
+        for state in 0..n {
+            let key = (self.accepting[state], self.outgoing(state));
+            let representative = *representatives.entry(key).or_insert(state);
+            // The initial state represents its group
+            if state == self.initial && representative != state {
+                mapping[representative] = state;
+                mapping[state] = state;
+            } else {
+                mapping[state] = representative;
+            }
+        }
+        // Follow the redirections of groups whose representative moved
+        let mapping: Vec<_> = mapping.iter().map(|&m| mapping[m]).collect();
+        if mapping.iter().enumerate().all(|(state, &m)| state == m) {
+            return false;
+        }
+        for transitions in &mut self.transitions {
+            for (_, target) in transitions.iter_mut() {
+                *target = mapping[*target];
+            }
+        }
+        let keep: Vec<_> = (0..n).map(|state| mapping[state] == state).collect();
+        self.retain(&keep);
+        true
+    }
+
+    /// Classes leading to the same target merged.
+    fn outgoing(&self, state: usize) -> BTreeMap<usize, CharClass> {
+        let mut outgoing: BTreeMap<usize, CharClass> = BTreeMap::new();
+        for (class, target) in &self.transitions[state] {
+            let merged = outgoing.entry(*target).or_default();
+            *merged = merged.union(class);
+        }
+        outgoing
+    }
+
+    /// Keeps the states marked in `keep`, renumbering them.
+    fn retain(&mut self, keep: &[bool]) {
+        let mut index = vec![usize::MAX; keep.len()];
+        let mut next = 0;
+        for (state, &kept) in keep.iter().enumerate() {
+            if kept {
+                index[state] = next;
+                next += 1;
+            }
+        }
+        let transitions = std::mem::take(&mut self.transitions);
+        self.transitions = transitions
+            .into_iter()
+            .zip(keep)
+            .filter(|(_, &kept)| kept)
+            .map(|(row, _)| row.into_iter().map(|(c, t)| (c, index[t])).collect())
+            .collect();
+        let accepting = std::mem::take(&mut self.accepting);
+        self.accepting = accepting
+            .into_iter()
+            .zip(keep)
+            .filter(|(_, &kept)| kept)
+            .map(|(accepting, _)| accepting)
+            .collect();
+        self.initial = index[self.initial];
+    }
+
+    fn into_nfa(mut self) -> Nfa {
+        // `Nfa::from_transitions` starts from state 0
+        self.transitions.swap(0, self.initial);
+        self.accepting.swap(0, self.initial);
+        let swap = |t: usize| match t {
+            0 => self.initial,
+            t if t == self.initial => 0,
+            t => t,
+        };
+        let transitions = self
+            .transitions
+            .iter()
+            .map(|row| row.iter().map(|(c, t)| (c.clone(), swap(*t))).collect())
+            .collect();
+        Nfa::from_transitions(transitions, &self.accepting)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simplify() {
+        let nfa = Nfa::from_str("ab|*a.b.b.").unwrap();
+        let simplified = nfa.simplify();
+        assert!(!simplified.has_epsilons());
+        assert!(simplified.state_count() < nfa.state_count());
+        assert!(simplified.is_equivalent(&nfa).is_yes());
+
+        // Both accepting ends share a state
+        let nfa = Nfa::from_str("ab.ac.|").unwrap();
+        let simplified = nfa.simplify();
+        assert_eq!(simplified.state_count(), 4);
+        assert!(simplified.is_equivalent(&nfa).is_yes());
+    }
+
+    #[test]
+    fn test_trim() {
+        // The sink state reached by `b` is dead
+        let dfa = Dfa::from_str("a")
+            .unwrap()
+            .union(&Dfa::from_str("a").unwrap());
+        let simplified = dfa.simplify();
+        assert_eq!(simplified.nfa.state_count(), 2);
+        assert!(simplified.test("a"));
+
+        let empty = Dfa::from_str("a")
+            .unwrap()
+            .difference(&Dfa::from_str("a").unwrap());
+        let simplified = empty.simplify();
+        assert_eq!(simplified.nfa.state_count(), 1);
+        assert!(simplified.is_empty().is_yes());
+    }
+
+    #[test]
+    fn test_merge_initial() {
+        let nfa = Nfa::from_str("a*").unwrap();
+        let simplified = nfa.simplify();
+        assert_eq!(simplified.state_count(), 1);
+        assert!(simplified.test(""));
+        assert!(simplified.test("aaa"));
+        assert!(!simplified.test("b"));
+    }
+}
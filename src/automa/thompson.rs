@@ -0,0 +1,207 @@
+use crate::class::CharClass;
+
+use super::{
+    error::NfaError,
+    nfa::{Draft, Node},
+    Nfa, State,
+};
+
+/// Linked list of accepting states, joined in constant time.
+#[derive(Clone, Copy)]
+struct Ends {
+    head: usize,
+    tail: usize,
+}
+
+/// Sub-automaton whose accepting states are still to be linked.
+struct Fragment {
+    start: State,
+    ends: Ends,
+}
+
+/// Arena every fragment is built in, so that no combinator copies a graph.
+struct Thompson {
+    draft: Draft,
+    /// Nodes of the [`Ends`] lists, with the index of the next one.
+    ends: Vec<(State, Option<usize>)>,
+}
+
+impl Thompson {
+    fn state(&mut self) -> Result<State, NfaError> {
+        self.draft.add_state()
+    }
+
+    fn single(&mut self, state: State) -> Ends {
+        self.ends.push((state, None));
+        let index = self.ends.len() - 1;
+        Ends {
+            head: index,
+            tail: index,
+        }
+    }
+
+    fn join(&mut self, a: Ends, b: Ends) -> Ends {
+        self.ends[a.tail].1 = Some(b.head);
+        Ends {
+            head: a.head,
+            tail: b.tail,
+        }
+    }
+
+    fn states(&self, ends: Ends) -> Vec<State> {
+        let mut states = vec![];
+        let mut next = Some(ends.head);
+        while let Some(index) = next {
+            states.push(self.ends[index].0);
+            next = self.ends[index].1;
+        }
+        states
+    }
+
+    /// Links every end to `target` through an epsilon edge.
+    fn patch(&mut self, ends: Ends, target: State) {
+        for state in self.states(ends) {
+            self.draft.add_edge(state, None, target);
+        }
+    }
+
+    /// Accepts any single char of `class` through a single edge.
+    fn literal(&mut self, class: CharClass) -> Result<Fragment, NfaError> {
+        let start = self.state()?;
+        let end = self.state()?;
+        self.draft.add_edge(start, Some(class), end);
+        Ok(Fragment {
+            start,
+            ends: self.single(end),
+        })
+    }
+
+    fn catenation(&mut self, lhs: Fragment, rhs: Fragment) -> Fragment {
+        self.patch(lhs.ends, rhs.start);
+        Fragment {
+            start: lhs.start,
+            ends: rhs.ends,
+        }
+    }
+
+    fn alternation(&mut self, lhs: Fragment, rhs: Fragment) -> Result<Fragment, NfaError> {
+        let start = self.state()?;
+        self.draft.add_edge(start, None, lhs.start);
+        self.draft.add_edge(start, None, rhs.start);
+        Ok(Fragment {
+            start,
+            ends: self.join(lhs.ends, rhs.ends),
+        })
+    }
+
+    fn zero_or_one(&mut self, fragment: Fragment) -> Result<Fragment, NfaError> {
+        let start = self.state()?;
+        self.draft.add_edge(start, None, fragment.start);
+        let ends = self.single(start);
+        Ok(Fragment {
+            start,
+            ends: self.join(ends, fragment.ends),
+        })
+    }
+
+    fn zero_or_more(&mut self, fragment: Fragment) -> Result<Fragment, NfaError> {
+        let start = self.state()?;
+        self.draft.add_edge(start, None, fragment.start);
+        self.patch(fragment.ends, start);
+        Ok(Fragment {
+            start,
+            ends: self.single(start),
+        })
+    }
+
+    fn one_or_more(&mut self, fragment: Fragment) -> Result<Fragment, NfaError> {
+        let end = self.state()?;
+        self.patch(fragment.ends, end);
+        self.draft.add_edge(end, None, fragment.start);
+        Ok(Fragment {
+            start: fragment.start,
+            ends: self.single(end),
+        })
+    }
+}
+
+impl Nfa {
+    /// Linear in the length of the pattern.
+    pub(super) fn thompson(nodes: Vec<Node>, max_states: Option<usize>) -> Result<Nfa, NfaError> {
+        let mut thompson = Thompson {
+            draft: Draft::new(max_states),
+            ends: vec![],
+        };
+        let mut stack: Vec<Fragment> = vec![];
+        for node in nodes {
+            let fragment = match node {
+                Node::Char(c) => thompson.literal(CharClass::from_char(c))?,
+                Node::Class(class) => thompson.literal(class)?,
+                Node::Catenation | Node::Alternation => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    match node {
+                        Node::Catenation => thompson.catenation(lhs, rhs),
+                        _ => thompson.alternation(lhs, rhs)?,
+                    }
+                }
+                Node::ZeroOrOne => thompson.zero_or_one(stack.pop().unwrap())?,
+                Node::ZeroOrMore => thompson.zero_or_more(stack.pop().unwrap())?,
+                Node::OneOrMore => thompson.one_or_more(stack.pop().unwrap())?,
+            };
+            stack.push(fragment);
+        }
+        // `Node::parse` has checked that there is a last sub-pattern
+        let fragment = stack.pop().unwrap();
+        let accepted_states = thompson.states(fragment.ends);
+        Ok(thompson.draft.finish(fragment.start, accepted_states))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_literal_charater() {
+        let nfa = Nfa::from_str("a").unwrap();
+        assert_eq!(nfa.state_count(), 2);
+        assert_eq!(nfa.transition_count(), 1);
+        assert_eq!(
+            nfa.edges(nfa.initial_state).next().unwrap().0,
+            Some(&CharClass::from_char('a'))
+        );
+    }
+
+    #[test]
+    fn test_combinators() {
+        for (expr, nodes, edges) in [
+            ("ab.", 4, 3),
+            ("ab|", 5, 4),
+            ("a?", 3, 2),
+            ("a*", 3, 3),
+            ("a+", 3, 3),
+        ] {
+            let nfa = Nfa::from_str(expr).unwrap();
+            assert_eq!(nfa.state_count(), nodes, "{expr}");
+            assert_eq!(nfa.transition_count(), edges, "{expr}");
+        }
+        let nfa = Nfa::from_str("ab|c?|").unwrap();
+        assert_eq!(nfa.accepted_states().count(), 4);
+    }
+
+    #[test]
+    fn test_long_pattern() {
+        // Left-deep catenation and right-deep alternation of many symbols
+        let n = 50_000;
+        let expr = format!("a{}", "b.".repeat(n));
+        let nfa = Nfa::from_str(&expr).unwrap();
+        assert_eq!(nfa.state_count(), 2 * (n + 1));
+        assert!(nfa.test(&format!("a{}", "b".repeat(n))));
+
+        let expr = format!("{}{}", "ab.".repeat(n), "|".repeat(n - 1));
+        let nfa = Nfa::from_str(&expr).unwrap();
+        assert_eq!(nfa.state_count(), 5 * n - 1);
+        assert_eq!(nfa.accepted_states().count(), n);
+    }
+}
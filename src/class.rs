@@ -0,0 +1,272 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::unicode;
+
+/// A set of chars stored as sorted, non-overlapping and non-adjacent ranges.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct CharClass {
+    ranges: Vec<(char, char)>,
+}
+
+impl CharClass {
+    pub fn new(ranges: impl IntoIterator<Item = (char, char)>) -> Self {
+        let mut ranges: Vec<_> = ranges
+            .into_iter()
+            .map(|(start, end)| (start.min(end), start.max(end)))
+            .collect();
+        ranges.sort_unstable();
+        let mut merged: Vec<(char, char)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= increment(last.1) => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        Self { ranges: merged }
+    }
+
+    pub fn from_char(c: char) -> Self {
+        Self {
+            ranges: vec![(c, c)],
+        }
+    }
+
+    pub fn any() -> Self {
+        Self {
+            ranges: vec![('\0', char::MAX)],
+        }
+    }
+
+    /// As in `\p{name}`, `Any` and `ASCII` included.
+    pub fn unicode_property(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "any" => Some(Self::any()),
+            "ascii" => Some(Self::new([('\0', '\x7F')])),
+            _ => unicode::property_ranges(name).map(|ranges| Self::new(ranges.iter().copied())),
+        }
+    }
+
+    /// As in `[[:alpha:]]`, in the POSIX locale.
+    pub fn posix(name: &str) -> Option<Self> {
+        let ranges: &[(char, char)] = match name {
+            "alnum" => &[('0', '9'), ('A', 'Z'), ('a', 'z')],
+            "alpha" => &[('A', 'Z'), ('a', 'z')],
+            "blank" => &[('\t', '\t'), (' ', ' ')],
+            "cntrl" => &[('\0', '\x1F'), ('\x7F', '\x7F')],
+            "digit" => &[('0', '9')],
+            "graph" => &[('!', '~')],
+            "lower" => &[('a', 'z')],
+            "print" => &[(' ', '~')],
+            "punct" => &[('!', '/'), (':', '@'), ('[', '`'), ('{', '~')],
+            "space" => &[('\t', '\r'), (' ', ' ')],
+            "upper" => &[('A', 'Z')],
+            "word" => &[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')],
+            "xdigit" => &[('0', '9'), ('A', 'F'), ('a', 'f')],
+            _ => return None,
+        };
+        Some(Self::new(ranges.iter().copied()))
+    }
+
+    pub fn ranges(&self) -> &[(char, char)] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The only member of the class, if it has exactly one.
+    pub fn single(&self) -> Option<char> {
+        match self.ranges[..] {
+            [(start, end)] if start == end => Some(start),
+            _ => None,
+        }
+    }
+
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.ranges.iter().flat_map(|&(start, end)| start..=end)
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.ranges
+            .binary_search_by(|&(start, end)| {
+                if end < c {
+                    std::cmp::Ordering::Less
+                } else if start > c {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .is_ok()
+    }
+
+    pub fn union(&self, other: &CharClass) -> Self {
+        Self::new(self.ranges.iter().chain(other.ranges.iter()).copied())
+    }
+
+    pub fn negate(&self) -> Self {
+        let mut ranges = vec![];
+        let mut next = Some('\0');
+        for &(start, end) in &self.ranges {
+            if let Some(next) = next.filter(|&next| next < start) {
+                ranges.push((next, decrement(start)));
+            }
+            next = (end != char::MAX).then(|| increment(end));
+        }
+        if let Some(next) = next {
+            ranges.push((next, char::MAX));
+        }
+        Self { ranges }
+    }
+
+    pub fn intersect(&self, other: &CharClass) -> Self {
+        self.negate().union(&other.negate()).negate()
+    }
+
+    pub fn difference(&self, other: &CharClass) -> Self {
+        self.intersect(&other.negate())
+    }
+
+    /// Disjoint pieces each inside or outside of every class.
+    pub fn partition<'a>(classes: impl IntoIterator<Item = &'a CharClass>) -> Vec<CharClass> {
+        let mut pieces: Vec<CharClass> = vec![];
+        for class in classes {
+            let mut rest = class.clone();
+            let mut next = Vec::with_capacity(pieces.len() + 1);
+            for piece in pieces {
+                let inside = piece.intersect(class);
+                let outside = piece.difference(class);
+                rest = rest.difference(&piece);
+                next.extend([inside, outside].into_iter().filter(|p| !p.is_empty()));
+            }
+            if !rest.is_empty() {
+                next.push(rest);
+            }
+            pieces = next;
+        }
+        pieces
+    }
+
+    /// Under Unicode simple case folding.
+    pub fn case_fold(&self) -> Self {
+        let folded = self
+            .ranges
+            .iter()
+            .flat_map(|&(start, end)| unicode::simple_fold_range(start, end))
+            .map(|c| (c, c));
+        Self::new(self.ranges.iter().copied().chain(folded))
+    }
+}
+
+/// Next char, skipping the surrogate gap.
+fn increment(c: char) -> char {
+    match c {
+        '\u{D7FF}' => '\u{E000}',
+        char::MAX => char::MAX,
+        _ => char::from_u32(c as u32 + 1).unwrap(),
+    }
+}
+
+/// Previous char, skipping the surrogate gap.
+fn decrement(c: char) -> char {
+    match c {
+        '\u{E000}' => '\u{D7FF}',
+        '\0' => '\0',
+        _ => char::from_u32(c as u32 - 1).unwrap(),
+    }
+}
+
+impl Display for CharClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[")?;
+        for &(start, end) in &self.ranges {
+            if start == end {
+                write!(f, "{}", start.escape_debug())?;
+            } else {
+                write!(f, "{}-{}", start.escape_debug(), end.escape_debug())?;
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        let class = CharClass::new([('x', 'z'), ('a', 'c'), ('d', 'e'), ('b', 'b')]);
+        assert_eq!(class.ranges(), [('a', 'e'), ('x', 'z')]);
+        assert!(class.contains('d'));
+        assert!(!class.contains('f'));
+        assert_eq!(class.to_string(), "[a-ex-z]");
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let class = CharClass::new([('b', 'y')]);
+        assert_eq!(class.negate().ranges(), [('\0', 'a'), ('z', char::MAX)]);
+        assert_eq!(class.negate().negate(), class);
+        // The surrogate gap does not split ranges
+        assert_eq!(
+            CharClass::new([('\u{D7FF}', '\u{D7FF}'), ('\u{E000}', '\u{E001}')]).ranges(),
+            [('\u{D7FF}', '\u{E001}')]
+        );
+        assert_eq!(CharClass::any().negate(), CharClass::default());
+
+        let other = CharClass::new([('a', 'c'), ('x', 'z')]);
+        assert_eq!(class.intersect(&other).ranges(), [('b', 'c'), ('x', 'y')]);
+        assert_eq!(class.difference(&other).ranges(), [('d', 'w')]);
+        assert_eq!(class.union(&other).ranges(), [('a', 'z')]);
+    }
+
+    #[test]
+    fn test_partition() {
+        let classes = [
+            CharClass::new([('a', 'z')]),
+            CharClass::new([('m', 'm'), ('0', '9')]),
+        ];
+        let pieces = CharClass::partition(&classes);
+        assert_eq!(pieces.len(), 3);
+        assert!(pieces.contains(&CharClass::new([('a', 'l'), ('n', 'z')])));
+        assert!(pieces.contains(&CharClass::from_char('m')));
+        assert!(pieces.contains(&CharClass::new([('0', '9')])));
+    }
+
+    #[test]
+    fn test_unicode_property() {
+        let letter = CharClass::unicode_property("L").unwrap();
+        assert!(letter.contains('a') && letter.contains('λ') && !letter.contains('1'));
+        let ascii = CharClass::unicode_property("ASCII").unwrap();
+        assert_eq!(ascii.ranges(), [('\0', '\x7F')]);
+        assert!(CharClass::unicode_property("Nope").is_none());
+    }
+
+    #[test]
+    fn test_posix() {
+        let punct = CharClass::posix("punct").unwrap();
+        assert!(punct.contains('!') && punct.contains('_') && punct.contains('~'));
+        assert!(!punct.contains('a') && !punct.contains(' '));
+        assert!(CharClass::posix("alpha").unwrap().contains('Q'));
+        assert!(CharClass::posix("Alpha").is_none());
+    }
+
+    #[test]
+    fn test_case_fold() {
+        let class = CharClass::new([('a', 'c'), ('1', '1')]).case_fold();
+        assert_eq!(class.ranges(), [('1', '1'), ('A', 'C'), ('a', 'c')]);
+        assert_eq!(CharClass::from_char('1').case_fold().single(), Some('1'));
+        assert_eq!(
+            CharClass::from_char('k').case_fold().ranges(),
+            [('K', 'K'), ('k', 'k'), ('\u{212A}', '\u{212A}')]
+        );
+        assert_eq!(
+            CharClass::from_char('ς').case_fold().ranges(),
+            [('Σ', 'Σ'), ('ς', 'σ')]
+        );
+    }
+}
@@ -1,2 +1,4 @@
 pub mod automa;
+pub mod class;
+mod unicode;
 pub mod vm;
@@ -0,0 +1,79 @@
+mod case_folding;
+mod general_category;
+mod script;
+
+use case_folding::CASE_FOLDING_SIMPLE;
+
+/// Case variants of every char in `start..=end` that has some.
+pub fn simple_fold_range(start: char, end: char) -> impl Iterator<Item = char> {
+    let i = CASE_FOLDING_SIMPLE.partition_point(|&(c, _)| c < start);
+    CASE_FOLDING_SIMPLE[i..]
+        .iter()
+        .take_while(move |&&(c, _)| c <= end)
+        .flat_map(|&(_, variants)| variants.iter().copied())
+}
+
+/// `gc=`, `sc=` and their long forms may prefix `name`, compared ignoring
+/// case, spaces, `-` and `_`.
+pub fn property_ranges(name: &str) -> Option<&'static [(char, char)]> {
+    let (prop, value) = match name.split_once('=') {
+        Some((prop, value)) => (Some(normalize(prop)), normalize(value)),
+        None => (None, normalize(name)),
+    };
+    let lookup = |table: &[(&str, &'static [(char, char)])]| {
+        table
+            .binary_search_by_key(&value.as_str(), |&(name, _)| name)
+            .ok()
+            .map(|i| table[i].1)
+    };
+    match prop.as_deref() {
+        None => lookup(general_category::BY_NAME).or_else(|| lookup(script::BY_NAME)),
+        Some("gc" | "generalcategory") => lookup(general_category::BY_NAME),
+        Some("sc" | "script") => lookup(script::BY_NAME),
+        Some(_) => None,
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simple_fold() {
+        let simple_fold = |c| simple_fold_range(c, c).collect::<Vec<_>>();
+        assert_eq!(simple_fold('k'), ['K', '\u{212A}']);
+        assert_eq!(simple_fold('\u{212A}'), ['K', 'k']);
+        assert_eq!(simple_fold('σ'), ['Σ', 'ς']);
+        assert_eq!(simple_fold('1'), []);
+        assert_eq!(
+            simple_fold_range('a', 'c').collect::<Vec<_>>(),
+            ['A', 'B', 'C']
+        );
+    }
+
+    #[test]
+    fn test_property_ranges() {
+        let contains = |name, c| {
+            property_ranges(name)
+                .unwrap()
+                .iter()
+                .any(|&(start, end)| start <= c && c <= end)
+        };
+        assert!(contains("L", 'ж'));
+        assert!(contains("Uppercase_Letter", 'Ж'));
+        assert!(!contains("lu", 'ж'));
+        assert!(contains("Greek", 'λ'));
+        assert!(contains("sc=Grek", 'λ'));
+        assert!(!contains("Script=Greek", 'l'));
+        assert!(contains("gc = Nd", '٣'));
+        assert!(property_ranges("sc=Letter").is_none());
+        assert!(property_ranges("Klingon").is_none());
+    }
+}
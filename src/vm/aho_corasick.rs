@@ -0,0 +1,301 @@
+/// Matcher for a set of literals, in a single pass over the haystack.
+#[derive(Debug, Clone)]
+pub struct AhoCorasick {
+    states: Vec<State>,
+    /// Length in bytes of each pattern.
+    lens: Vec<usize>,
+    max_len: usize,
+}
+
+/// Occurrence of pattern `pattern` over the bytes `start..end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    pub pattern: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+const ROOT: u32 = 0;
+
+/// Trie node, standing for the text read from the root to it.
+#[derive(Debug, Clone, Default)]
+struct State {
+    /// Children sorted by char.
+    next: Vec<(char, u32)>,
+    /// Longest proper suffix of the text that is also a node.
+    fail: u32,
+    /// Patterns equal to the text, in increasing order.
+    patterns: Vec<u32>,
+    /// Longest proper suffix of the text that is a pattern.
+    dict: Option<u32>,
+}
+
+impl AhoCorasick {
+    pub fn new<I, P>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        let mut states = vec![State::default()];
+        let mut lens = vec![];
+        for (index, pattern) in patterns.into_iter().enumerate() {
+            let pattern = pattern.as_ref();
+            let mut state = ROOT;
+            for c in pattern.chars() {
+                let next = &states[state as usize].next;
+                state = match next.binary_search_by_key(&c, |&(c1, _)| c1) {
+                    Ok(i) => next[i].1,
+                    Err(i) => {
+                        let child = states.len() as u32;
+                        states[state as usize].next.insert(i, (c, child));
+                        states.push(State::default());
+                        child
+                    }
+                };
+            }
+            states[state as usize].patterns.push(index as u32);
+            lens.push(pattern.len());
+        }
+
+        // Breadth first, so that the failure of every parent is known
+        let mut queue = std::collections::VecDeque::from([ROOT]);
+        while let Some(parent) = queue.pop_front() {
+            for (c, child) in states[parent as usize].next.clone() {
+                let fail = if parent == ROOT {
+                    ROOT
+                } else {
+                    let mut state = states[parent as usize].fail;
+                    loop {
+                        if let Some(next) = goto(&states, state, c) {
+                            break next;
+                        }
+                        if state == ROOT {
+                            break ROOT;
+                        }
+                        state = states[state as usize].fail;
+                    }
+                };
+                let fail_state = &states[fail as usize];
+                let dict = if fail_state.patterns.is_empty() {
+                    fail_state.dict
+                } else {
+                    Some(fail)
+                };
+                states[child as usize].fail = fail;
+                states[child as usize].dict = dict;
+                queue.push_back(child);
+            }
+        }
+
+        let max_len = lens.iter().copied().max().unwrap_or(0);
+        Self {
+            states,
+            lens,
+            max_len,
+        }
+    }
+
+    pub fn patterns_len(&self) -> usize {
+        self.lens.len()
+    }
+
+    fn step(&self, mut state: u32, c: char) -> u32 {
+        loop {
+            if let Some(next) = goto(&self.states, state, c) {
+                return next;
+            }
+            if state == ROOT {
+                return ROOT;
+            }
+            state = self.states[state as usize].fail;
+        }
+    }
+
+    /// Whether some pattern occurs in `haystack`.
+    pub fn is_match(&self, haystack: &str) -> bool {
+        self.find_overlapping_iter(haystack).next().is_some()
+    }
+
+    /// Whether some pattern is a prefix of `haystack`.
+    pub fn is_match_at_start(&self, haystack: &str) -> bool {
+        let mut state = ROOT;
+        let mut chars = haystack.chars();
+        loop {
+            if !self.states[state as usize].patterns.is_empty() {
+                return true;
+            }
+            match chars.next().and_then(|c| goto(&self.states, state, c)) {
+                Some(next) => state = next,
+                None => return false,
+            }
+        }
+    }
+
+    /// By increasing end and then decreasing length.
+    pub fn find_overlapping_iter<'a>(&'a self, haystack: &'a str) -> OverlappingMatches<'a> {
+        OverlappingMatches {
+            ac: self,
+            haystack,
+            end: 0,
+            state: ROOT,
+            output: Some((ROOT, 0)),
+        }
+    }
+
+    /// Non-overlapping, the first pattern given winning at a position.
+    pub fn find_iter<'a>(&'a self, haystack: &'a str) -> Matches<'a> {
+        Matches {
+            ac: self,
+            haystack,
+            start: Some(0),
+        }
+    }
+
+    /// Leftmost-first occurrence starting at byte offset `start` or later.
+    fn find_at(&self, haystack: &str, start: usize) -> Option<Match> {
+        let mut best: Option<Match> = None;
+        for m in self.find_overlapping_iter(&haystack[start..]) {
+            let m = Match {
+                start: m.start + start,
+                end: m.end + start,
+                ..m
+            };
+            // Later occurrences cannot start at or before the best one
+            if best.is_some_and(|best| m.end > best.start + self.max_len) {
+                break;
+            }
+            if best.is_none_or(|best| (m.start, m.pattern) < (best.start, best.pattern)) {
+                best = Some(m);
+            }
+        }
+        best
+    }
+}
+
+fn goto(states: &[State], state: u32, c: char) -> Option<u32> {
+    let next = &states[state as usize].next;
+    next.binary_search_by_key(&c, |&(c1, _)| c1)
+        .ok()
+        .map(|i| next[i].1)
+}
+
+/// Iterator returned by [`AhoCorasick::find_overlapping_iter`].
+pub struct OverlappingMatches<'a> {
+    ac: &'a AhoCorasick,
+    haystack: &'a str,
+    /// Byte offset up to which the haystack was read.
+    end: usize,
+    state: u32,
+    /// State whose patterns are being reported, with the index of the next.
+    output: Option<(u32, usize)>,
+}
+
+impl Iterator for OverlappingMatches<'_> {
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Match> {
+        loop {
+            if let Some((state, i)) = self.output {
+                let node = &self.ac.states[state as usize];
+                if let Some(&pattern) = node.patterns.get(i) {
+                    self.output = Some((state, i + 1));
+                    let pattern = pattern as usize;
+                    return Some(Match {
+                        pattern,
+                        start: self.end - self.ac.lens[pattern],
+                        end: self.end,
+                    });
+                }
+                self.output = node.dict.map(|dict| (dict, 0));
+                continue;
+            }
+            let c = self.haystack[self.end..].chars().next()?;
+            self.end += c.len_utf8();
+            self.state = self.ac.step(self.state, c);
+            self.output = Some((self.state, 0));
+        }
+    }
+}
+
+/// Iterator returned by [`AhoCorasick::find_iter`].
+pub struct Matches<'a> {
+    ac: &'a AhoCorasick,
+    haystack: &'a str,
+    start: Option<usize>,
+}
+
+impl Iterator for Matches<'_> {
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Match> {
+        let m = self.ac.find_at(self.haystack, self.start?)?;
+        self.start = if m.end > m.start {
+            Some(m.end)
+        } else {
+            // Move past an empty match so as not to find it again
+            self.haystack[m.end..]
+                .chars()
+                .next()
+                .map(|c| m.end + c.len_utf8())
+        };
+        Some(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(matches: impl Iterator<Item = Match>) -> Vec<(usize, usize, usize)> {
+        matches.map(|m| (m.pattern, m.start, m.end)).collect()
+    }
+
+    #[test]
+    fn test_overlapping() {
+        let ac = AhoCorasick::new(["he", "she", "his", "hers"]);
+        assert_eq!(
+            spans(ac.find_overlapping_iter("ushers")),
+            vec![(1, 1, 4), (0, 2, 4), (3, 2, 6)]
+        );
+        assert!(ac.is_match("ahis"));
+        assert!(!ac.is_match("hi"));
+    }
+
+    #[test]
+    fn test_leftmost_first() {
+        let ac = AhoCorasick::new(["abcd", "bc", "ab"]);
+        assert_eq!(spans(ac.find_iter("xabcdbc")), vec![(0, 1, 5), (1, 5, 7)]);
+
+        let ac = AhoCorasick::new(["ab", "abcd"]);
+        assert_eq!(spans(ac.find_iter("abcd")), vec![(0, 0, 2)]);
+
+        let ac = AhoCorasick::new(["b", "abc"]);
+        assert_eq!(spans(ac.find_iter("abc")), vec![(1, 0, 3)]);
+
+        let ac = AhoCorasick::new(["", "a"]);
+        assert_eq!(
+            spans(ac.find_iter("ab")),
+            vec![(0, 0, 0), (0, 1, 1), (0, 2, 2)]
+        );
+    }
+
+    #[test]
+    fn test_at_start() {
+        let ac = AhoCorasick::new(["λόγος", "log"]);
+        assert!(ac.is_match_at_start("λόγοςx"));
+        assert!(ac.is_match_at_start("logos"));
+        assert!(!ac.is_match_at_start("xlog"));
+        assert_eq!(spans(ac.find_iter("a λόγος")), vec![(0, 2, 12)]);
+    }
+
+    #[test]
+    fn test_many_patterns() {
+        let keywords: Vec<_> = (0..5000).map(|i| format!("kw{i}x")).collect();
+        let ac = AhoCorasick::new(&keywords);
+        assert_eq!(ac.patterns_len(), 5000);
+        assert_eq!(
+            spans(ac.find_iter("see kw42x and kw4999x, not kw5000x")),
+            vec![(42, 4, 9), (4999, 14, 21)]
+        );
+    }
+}
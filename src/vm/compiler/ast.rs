@@ -1,17 +1,142 @@
-pub struct Group(pub(super) Vec<Expr>);
+use crate::class::CharClass;
 
+#[derive(Clone)]
+pub struct Group(pub(super) Vec<(GroupKind, Expr)>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GroupKind {
+    Capture {
+        index: usize,
+        name: Option<String>,
+    },
+    /// `(?:...)`, possibly with scoped flags as in `(?i:...)`
+    NonCapture(FlagsModifier),
+    /// `(?i)`, in effect up to the end of the enclosing group, unlike
+    /// `(?i:...)` which only covers its own contents
+    Flags(FlagsModifier),
+    LookAhead {
+        negate: bool,
+    },
+    LookBehind {
+        negate: bool,
+    },
+}
+
+/// Pattern dialect understood by the lexer and parser.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Syntax {
+    #[default]
+    Default,
+    /// POSIX ERE as in `grep -E`, with leftmost-longest matches.
+    PosixExtended,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Flags {
+    pub case_insensitive: bool,
+    pub multi_line: bool,
+    pub dot_all: bool,
+    pub verbose: bool,
+}
+
+/// Flags turned on and off by a flag group such as `(?i-s)`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlagsModifier {
+    pub on: Flags,
+    pub off: Flags,
+}
+
+impl FlagsModifier {
+    pub fn apply(&self, flags: Flags) -> Flags {
+        let apply = |flag: fn(&Flags) -> bool| (flag(&flags) || flag(&self.on)) && !flag(&self.off);
+        Flags {
+            case_insensitive: apply(|flags| flags.case_insensitive),
+            multi_line: apply(|flags| flags.multi_line),
+            dot_all: apply(|flags| flags.dot_all),
+            verbose: apply(|flags| flags.verbose),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Expr(pub(super) Vec<FactorConn>);
 
+#[derive(Clone)]
 pub struct FactorConn(pub(super) Vec<Factor>);
 
+/// Repeated terms carry whether the quantifier is lazy, as in `a*?`.
+#[derive(Clone)]
 pub enum Factor {
     Plain(Term),
-    ZeroOrOne(Term),
-    ZeroOrMore(Term),
-    OneOrMore(Term),
+    ZeroOrOne(Term, bool),
+    ZeroOrMore(Term, bool),
+    OneOrMore(Term, bool),
 }
 
+#[derive(Clone)]
 pub enum Term {
     Char(char),
+    /// Complemented when negated
+    Class(CharClass, bool),
+    Dot,
+    Caret,
+    Dollar,
     Group(Group),
+    Backref(usize),
+    /// Strings matched by every expression, as `a&b`
+    And(Vec<Expr>),
+    /// Strings not matched by the factor, as `~a`
+    Not(Box<Factor>),
+}
+
+impl Group {
+    /// Longest text the group can match in chars, `None` if unbounded.
+    pub fn max_len(&self) -> Option<usize> {
+        self.0.iter().try_fold(0, |len, (kind, expr)| match kind {
+            GroupKind::Capture { .. } | GroupKind::NonCapture(_) => Some(len + expr.max_len()?),
+            GroupKind::Flags(_) | GroupKind::LookAhead { .. } | GroupKind::LookBehind { .. } => {
+                Some(len)
+            }
+        })
+    }
+}
+
+impl Expr {
+    pub fn max_len(&self) -> Option<usize> {
+        self.0
+            .iter()
+            .try_fold(0, |len, factor_conn| Some(len.max(factor_conn.max_len()?)))
+    }
+}
+
+impl FactorConn {
+    pub fn max_len(&self) -> Option<usize> {
+        self.0
+            .iter()
+            .try_fold(0, |len, factor| Some(len + factor.max_len()?))
+    }
+}
+
+impl Factor {
+    pub fn max_len(&self) -> Option<usize> {
+        match self {
+            Factor::Plain(term) | Factor::ZeroOrOne(term, _) => term.max_len(),
+            Factor::ZeroOrMore(term, _) | Factor::OneOrMore(term, _) => match term.max_len()? {
+                0 => Some(0),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl Term {
+    pub fn max_len(&self) -> Option<usize> {
+        match self {
+            Term::Char(_) | Term::Class(..) | Term::Dot => Some(1),
+            Term::Caret | Term::Dollar => Some(0),
+            Term::Group(group) => group.max_len(),
+            Term::Backref(_) | Term::Not(_) => None,
+            Term::And(exprs) => exprs.iter().filter_map(Expr::max_len).min(),
+        }
+    }
 }
@@ -0,0 +1,224 @@
+use crate::{
+    class::CharClass,
+    vm::{inst::Inst, program::Program, AhoCorasick, Regex},
+};
+
+use super::{
+    ast::{Expr, Factor, FactorConn, Flags, FlagsModifier, Group, GroupKind, Syntax, Term},
+    derivative::Derivatives,
+    error::CompileError,
+    lexer,
+    literal::{self, Literals},
+    parser, reverse, transformer,
+};
+
+/// Flags set here are the defaults inline flag groups override.
+#[derive(Debug, Clone)]
+pub struct Builder {
+    flags: Flags,
+    syntax: Syntax,
+    backtracking: bool,
+    reverse: bool,
+    nest_limit: usize,
+    max_insts: Option<usize>,
+    max_steps: Option<u64>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            flags: Flags::default(),
+            syntax: Syntax::default(),
+            backtracking: false,
+            reverse: false,
+            nest_limit: parser::DEFAULT_NEST_LIMIT,
+            max_insts: None,
+            max_steps: None,
+        }
+    }
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Matches letters regardless of their case, as `(?i)`.
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut Self {
+        self.flags.case_insensitive = yes;
+        self
+    }
+
+    /// Makes `^` and `$` match at line boundaries, as `(?m)`.
+    pub fn multi_line(&mut self, yes: bool) -> &mut Self {
+        self.flags.multi_line = yes;
+        self
+    }
+
+    /// Makes `.` match `\n` too, as `(?s)`.
+    pub fn dot_all(&mut self, yes: bool) -> &mut Self {
+        self.flags.dot_all = yes;
+        self
+    }
+
+    /// Ignores whitespace and `#` comments in the pattern, as `(?x)`.
+    pub fn verbose(&mut self, yes: bool) -> &mut Self {
+        self.flags.verbose = yes;
+        self
+    }
+
+    /// Selects the pattern syntax. POSIX patterns also match leftmost-longest.
+    pub fn syntax(&mut self, syntax: Syntax) -> &mut Self {
+        self.syntax = syntax;
+        self
+    }
+
+    /// Allows backreferences and unbounded lookbehinds.
+    pub fn backtracking(&mut self, yes: bool) -> &mut Self {
+        self.backtracking = yes;
+        self
+    }
+
+    /// Compiles a program matching the mirror image of the pattern, for
+    /// `Interpreter::rfind`. Backreferences are rejected.
+    pub fn reverse(&mut self, yes: bool) -> &mut Self {
+        self.reverse = yes;
+        self
+    }
+
+    /// Rejects groups nested deeper than `limit`, 128 by default.
+    pub fn nest_limit(&mut self, limit: usize) -> &mut Self {
+        self.nest_limit = limit;
+        self
+    }
+
+    /// Rejects programs over `limit` instructions, lookarounds included, and
+    /// patterns whose intervals spell out more than `limit` terms.
+    pub fn max_insts(&mut self, limit: usize) -> &mut Self {
+        self.max_insts = Some(limit);
+        self
+    }
+
+    /// Step budget of the `try_` searches.
+    pub fn max_steps(&mut self, steps: u64) -> &mut Self {
+        self.max_steps = Some(steps);
+        self
+    }
+
+    pub fn build(&self, expr: &str) -> Result<Program, CompileError> {
+        let (ast, capture_names) = self.parse(expr)?;
+        self.compile(ast, capture_names)
+    }
+
+    /// AST of `expr`, reversed if asked, and the names of its groups.
+    fn parse(&self, expr: &str) -> Result<(Group, Vec<Option<String>>), CompileError> {
+        let mut lexer = lexer::Lexer::new(expr.chars()).with_syntax(self.syntax);
+        let verbose = lexer.verbose();
+        let mut parser = parser::Parser::new(&mut lexer)
+            .with_verbose(verbose)
+            .with_flags(self.flags)
+            .with_syntax(self.syntax)
+            .with_nest_limit(self.nest_limit)
+            .with_max_size(self.max_insts.unwrap_or(parser::DEFAULT_MAX_SIZE));
+        let mut ast = parser.parse()?;
+        if self.reverse {
+            ast = reverse::reverse(ast, self.flags)?;
+        }
+        Ok((ast, parser.capture_names().to_vec()))
+    }
+
+    fn compile(
+        &self,
+        ast: Group,
+        capture_names: Vec<Option<String>>,
+    ) -> Result<Program, CompileError> {
+        let mut transformer = transformer::Transformer::new(self.flags);
+        let literals = Literals::new(&ast, self.flags);
+        let alternatives = literal::alternatives(&ast, self.flags);
+        let mut prog = Program::new(transformer.transform(ast), capture_names)
+            .with_leftmost_longest(self.syntax == Syntax::PosixExtended)
+            .with_reversed(self.reverse)
+            .with_max_steps(self.max_steps)
+            .with_literals(literals);
+        if let Some(alternatives) = alternatives {
+            prog = prog.with_aho_corasick(AhoCorasick::new(alternatives));
+        }
+        let insts = flatten(&prog);
+        if let Some(limit) = self.max_insts.filter(|&limit| insts.len() > limit) {
+            return Err(CompileError::TooManyInsts(limit));
+        }
+        for inst in insts {
+            match inst {
+                Inst::Backref(n) if *n == 0 || *n >= prog.captures_len() => {
+                    return Err(CompileError::InvalidBackref(*n))
+                }
+                Inst::Backref(n) if !self.backtracking => {
+                    return Err(CompileError::UnsupportedBackref(*n))
+                }
+                Inst::Look(look) if look.behind && look.max_len.is_none() && !self.backtracking => {
+                    return Err(CompileError::UnboundedLookbehind)
+                }
+                _ => {}
+            }
+        }
+        Ok(prog)
+    }
+}
+
+impl Builder {
+    /// Enables `&` and `~`.
+    pub fn build_derivatives(&self, expr: &str) -> Result<Derivatives, CompileError> {
+        let mut lexer = lexer::Lexer::new(expr.chars()).with_syntax(self.syntax);
+        let verbose = lexer.verbose();
+        let mut parser = parser::Parser::new(&mut lexer)
+            .with_verbose(verbose)
+            .with_flags(self.flags)
+            .with_syntax(self.syntax)
+            .with_nest_limit(self.nest_limit)
+            .with_max_size(self.max_insts.unwrap_or(parser::DEFAULT_MAX_SIZE))
+            .with_boolean_ops(true);
+        Derivatives::new(parser.parse()?, self.flags)
+    }
+}
+
+/// Most states of the automaton a [`Regex`] builds for searching.
+const MAX_REGEX_DFA_STATES: usize = 256;
+
+impl Builder {
+    /// Also builds the automaton of `.*(expr).*` from the derivatives, when
+    /// the pattern allows it and it stays small.
+    pub fn build_regex(&self, expr: &str) -> Result<Regex, CompileError> {
+        let (ast, capture_names) = self.parse(expr)?;
+        let prog = self.compile(ast.clone(), capture_names)?;
+        let dfa = if prog.aho_corasick().is_none() {
+            Derivatives::new(unanchored(ast), self.flags)
+                .ok()
+                .and_then(|derivatives| derivatives.to_bounded_dfa(MAX_REGEX_DFA_STATES))
+        } else {
+            None
+        };
+        Ok(Regex::from_parts(prog, dfa))
+    }
+}
+
+/// Group matching the strings that contain a match of `group`.
+fn unanchored(group: Group) -> Group {
+    let any = || Factor::ZeroOrMore(Term::Class(CharClass::any(), false), false);
+    let conn = FactorConn(vec![any(), Factor::Plain(Term::Group(group)), any()]);
+    Group(vec![(
+        GroupKind::NonCapture(FlagsModifier::default()),
+        Expr(vec![conn]),
+    )])
+}
+
+/// Collects the instructions of `prog` and of all its lookaround sub-programs.
+fn flatten(prog: &[Inst]) -> Vec<&Inst> {
+    let mut insts = vec![];
+    for inst in prog {
+        insts.push(inst);
+        if let Inst::Look(look) = inst {
+            insts.extend(flatten(&look.prog));
+        }
+    }
+    insts
+}
@@ -0,0 +1,425 @@
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    rc::Rc,
+};
+
+use memchr::memmem;
+
+use crate::{
+    automa::{Dfa, Nfa},
+    class::CharClass,
+};
+
+use super::{
+    ast::{Expr, Factor, FactorConn, Flags, Group, GroupKind, Term},
+    error::CompileError,
+    literal::Literals,
+};
+
+/// Matcher over the Brzozowski derivatives of a pattern, which must match
+/// whole strings. Anchors, lookarounds and backreferences are rejected.
+#[derive(Debug, Clone)]
+pub struct Derivatives {
+    re: Re,
+    literals: Literals,
+}
+
+impl Derivatives {
+    pub(super) fn new(group: Group, flags: Flags) -> Result<Self, CompileError> {
+        let literals = Literals::new(&group, flags);
+        let re = Lowering { flags }.group(group)?;
+        Ok(Self { re, literals })
+    }
+
+    /// Derives one char at a time once the literals are known to be present.
+    pub fn is_match(&self, input: &str) -> bool {
+        if !input.starts_with(self.literals.prefix())
+            || !input.ends_with(self.literals.suffix())
+            || memmem::find(input.as_bytes(), self.literals.inner().as_bytes()).is_none()
+        {
+            return false;
+        }
+        let mut re = self.re.clone();
+        for c in input.chars() {
+            if re == Re::Empty {
+                return false;
+            }
+            re = re.derive(c);
+        }
+        re.nullable()
+    }
+
+    /// DFA whose states are the distinct derivatives of the pattern.
+    pub fn to_dfa(&self) -> Dfa {
+        self.to_bounded_dfa(usize::MAX).unwrap()
+    }
+
+    /// Gives up past `max_states` states.
+    pub fn to_bounded_dfa(&self, max_states: usize) -> Option<Dfa> {
+        let mut classes = vec![CharClass::any()];
+        self.re.collect_classes(&mut classes);
+        let alphabet = CharClass::partition(&classes);
+
+        let mut index = HashMap::from([(self.re.clone(), 0)]);
+        let mut states = vec![self.re.clone()];
+        let mut transitions = vec![];
+        let mut queue = VecDeque::from([0]);
+        while let Some(i) = queue.pop_front() {
+            let mut row = vec![];
+            for piece in &alphabet {
+                let next = states[i].derive(piece.ranges()[0].0);
+                if next == Re::Empty {
+                    continue;
+                }
+                let target = *index.entry(next.clone()).or_insert_with(|| {
+                    states.push(next);
+                    queue.push_back(states.len() - 1);
+                    states.len() - 1
+                });
+                row.push((piece.clone(), target));
+            }
+            if states.len() > max_states {
+                return None;
+            }
+            transitions.push(row);
+        }
+        let accepting: Vec<_> = states.iter().map(Re::nullable).collect();
+        Some(Dfa::new(Nfa::from_transitions(transitions, &accepting)))
+    }
+}
+
+/// Normalized by smart constructors, so equivalent derivatives compare equal.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Re {
+    /// Matches nothing.
+    Empty,
+    /// Matches the empty string.
+    Epsilon,
+    Class(CharClass),
+    /// Nested to the right only.
+    Concat(Rc<Re>, Rc<Re>),
+    /// At least two items, with classes merged into one.
+    Or(BTreeSet<Re>),
+    /// At least two items, with classes merged into one.
+    And(BTreeSet<Re>),
+    Not(Rc<Re>),
+    Star(Rc<Re>),
+}
+
+impl Re {
+    fn anything() -> Re {
+        Re::Not(Rc::new(Re::Empty))
+    }
+
+    fn nullable(&self) -> bool {
+        match self {
+            Re::Empty | Re::Class(_) => false,
+            Re::Epsilon | Re::Star(_) => true,
+            Re::Concat(a, b) => a.nullable() && b.nullable(),
+            Re::Or(items) => items.iter().any(Re::nullable),
+            Re::And(items) => items.iter().all(Re::nullable),
+            Re::Not(re) => !re.nullable(),
+        }
+    }
+
+    /// Strings `s` such that `c` followed by `s` matches.
+    fn derive(&self, c: char) -> Re {
+        match self {
+            Re::Empty | Re::Epsilon => Re::Empty,
+            Re::Class(class) if class.contains(c) => Re::Epsilon,
+            Re::Class(_) => Re::Empty,
+            Re::Concat(a, b) => {
+                let derived = concat(a.derive(c), (**b).clone());
+                if a.nullable() {
+                    or([derived, b.derive(c)])
+                } else {
+                    derived
+                }
+            }
+            Re::Or(items) => or(items.iter().map(|re| re.derive(c))),
+            Re::And(items) => and(items.iter().map(|re| re.derive(c))),
+            Re::Not(re) => not(re.derive(c)),
+            Re::Star(re) => concat(re.derive(c), self.clone()),
+        }
+    }
+
+    fn collect_classes(&self, classes: &mut Vec<CharClass>) {
+        match self {
+            Re::Empty | Re::Epsilon => {}
+            Re::Class(class) => classes.push(class.clone()),
+            Re::Concat(a, b) => {
+                a.collect_classes(classes);
+                b.collect_classes(classes);
+            }
+            Re::Or(items) | Re::And(items) => {
+                for re in items {
+                    re.collect_classes(classes);
+                }
+            }
+            Re::Not(re) | Re::Star(re) => re.collect_classes(classes),
+        }
+    }
+}
+
+fn or(items: impl IntoIterator<Item = Re>) -> Re {
+    let mut set = BTreeSet::new();
+    let mut class = CharClass::default();
+    let mut add = |re| match re {
+        Re::Empty => {}
+        Re::Class(c) => class = class.union(&c),
+        re => {
+            set.insert(re);
+        }
+    };
+    for re in items {
+        match re {
+            Re::Or(items) => items.into_iter().for_each(&mut add),
+            re => add(re),
+        }
+    }
+    if set.contains(&Re::anything()) {
+        return Re::anything();
+    }
+    if !class.is_empty() {
+        set.insert(Re::Class(class));
+    }
+    match set.len() {
+        0 => Re::Empty,
+        1 => set.pop_first().unwrap(),
+        _ => Re::Or(set),
+    }
+}
+
+fn and(items: impl IntoIterator<Item = Re>) -> Re {
+    let mut set = BTreeSet::new();
+    let mut class: Option<CharClass> = None;
+    let mut add = |re| match re {
+        Re::Class(c) => class = Some(class.take().map_or(c.clone(), |class| class.intersect(&c))),
+        re => {
+            set.insert(re);
+        }
+    };
+    for re in items {
+        match re {
+            Re::And(items) => items.into_iter().for_each(&mut add),
+            re => add(re),
+        }
+    }
+    set.remove(&Re::anything());
+    if set.contains(&Re::Empty) {
+        return Re::Empty;
+    }
+    if let Some(class) = class {
+        // A class only matches strings of one char
+        if class.is_empty() || set.contains(&Re::Epsilon) {
+            return Re::Empty;
+        }
+        set.insert(Re::Class(class));
+    }
+    match set.len() {
+        0 => Re::anything(),
+        1 => set.pop_first().unwrap(),
+        _ => Re::And(set),
+    }
+}
+
+fn concat(a: Re, b: Re) -> Re {
+    match (a, b) {
+        (Re::Empty, _) | (_, Re::Empty) => Re::Empty,
+        (Re::Epsilon, re) | (re, Re::Epsilon) => re,
+        (Re::Concat(a1, a2), b) => concat((*a1).clone(), concat((*a2).clone(), b)),
+        (a, b) => Re::Concat(Rc::new(a), Rc::new(b)),
+    }
+}
+
+fn star(re: Re) -> Re {
+    match re {
+        Re::Empty | Re::Epsilon => Re::Epsilon,
+        Re::Star(_) => re,
+        re => Re::Star(Rc::new(re)),
+    }
+}
+
+fn not(re: Re) -> Re {
+    match re {
+        Re::Not(re) => (*re).clone(),
+        re => Re::Not(Rc::new(re)),
+    }
+}
+
+/// Turns the AST into a [`Re`], applying the flags in scope.
+struct Lowering {
+    flags: Flags,
+}
+
+impl Lowering {
+    fn group(&mut self, group: Group) -> Result<Re, CompileError> {
+        let mut res = vec![];
+        for (kind, expr) in group.0 {
+            let flags = self.flags;
+            let re = match kind {
+                GroupKind::Flags(modifier) => {
+                    self.flags = modifier.apply(flags);
+                    continue;
+                }
+                GroupKind::Capture { .. } => self.expr(expr)?,
+                GroupKind::NonCapture(modifier) => {
+                    self.flags = modifier.apply(flags);
+                    self.expr(expr)?
+                }
+                GroupKind::LookAhead { .. } | GroupKind::LookBehind { .. } => {
+                    return Err(CompileError::UnsupportedDerivative("lookarounds"))
+                }
+            };
+            self.flags = flags;
+            res.push(re);
+        }
+        Ok(res.into_iter().rev().fold(Re::Epsilon, |b, a| concat(a, b)))
+    }
+
+    fn expr(&mut self, expr: Expr) -> Result<Re, CompileError> {
+        let items = expr
+            .0
+            .into_iter()
+            .map(|conn| self.factor_conn(conn))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(or(items))
+    }
+
+    fn factor_conn(&mut self, conn: FactorConn) -> Result<Re, CompileError> {
+        let res = conn
+            .0
+            .into_iter()
+            .map(|factor| self.factor(factor))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(res.into_iter().rev().fold(Re::Epsilon, |b, a| concat(a, b)))
+    }
+
+    /// Laziness only affects captures, so it is ignored.
+    fn factor(&mut self, factor: Factor) -> Result<Re, CompileError> {
+        Ok(match factor {
+            Factor::Plain(term) => self.term(term)?,
+            Factor::ZeroOrOne(term, _) => or([Re::Epsilon, self.term(term)?]),
+            Factor::ZeroOrMore(term, _) => star(self.term(term)?),
+            Factor::OneOrMore(term, _) => {
+                let re = self.term(term)?;
+                concat(re.clone(), star(re))
+            }
+        })
+    }
+
+    fn term(&mut self, term: Term) -> Result<Re, CompileError> {
+        let class = match term {
+            Term::Char(c) => CharClass::from_char(c),
+            Term::Class(class, negate) => {
+                let class = self.fold(class);
+                return Ok(Re::Class(if negate { class.negate() } else { class }));
+            }
+            Term::Dot if self.flags.dot_all => CharClass::any(),
+            Term::Dot => CharClass::from_char('\n').negate(),
+            Term::Caret | Term::Dollar => {
+                return Err(CompileError::UnsupportedDerivative("anchors"))
+            }
+            Term::Backref(_) => return Err(CompileError::UnsupportedDerivative("backreferences")),
+            Term::Group(group) => return self.group(group),
+            Term::And(exprs) => {
+                let items = exprs
+                    .into_iter()
+                    .map(|expr| self.expr(expr))
+                    .collect::<Result<Vec<_>, _>>()?;
+                return Ok(and(items));
+            }
+            Term::Not(factor) => return Ok(not(self.factor(*factor)?)),
+        };
+        Ok(Re::Class(self.fold(class)))
+    }
+
+    fn fold(&self, class: CharClass) -> CharClass {
+        if self.flags.case_insensitive {
+            class.case_fold()
+        } else {
+            class
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vm::Builder;
+
+    use super::*;
+
+    fn derivatives(expr: &str) -> Derivatives {
+        Builder::new().build_derivatives(expr).unwrap()
+    }
+
+    #[test]
+    fn test_is_match() {
+        let d = derivatives("((a|b)*abb)");
+        assert!(d.is_match("abb"));
+        assert!(d.is_match("babaabb"));
+        assert!(!d.is_match("abba"));
+        assert!(!d.is_match(""));
+
+        // Even length words without `aa`
+        let d = derivatives("(((a|b)(a|b))*&~(.*aa.*))");
+        assert!(d.is_match(""));
+        assert!(d.is_match("abba"));
+        assert!(!d.is_match("baab"));
+        assert!(!d.is_match("aba"));
+
+        let d = Builder::new()
+            .case_insensitive(true)
+            .build_derivatives(r"([[:alpha:]]+[[:digit:]]?&~(?-i:abc))")
+            .unwrap();
+        assert!(d.is_match("ABC"));
+        assert!(d.is_match("ABC1"));
+        assert!(d.is_match("abd"));
+        assert!(!d.is_match("abc"));
+    }
+
+    #[test]
+    fn test_normalization() {
+        let a = || Re::Class(CharClass::from_char('a'));
+        let b = || Re::Class(CharClass::from_char('b'));
+        let ab = concat(a(), b());
+        assert_eq!(
+            or([ab.clone(), a(), ab.clone(), b()]),
+            or([b(), ab.clone(), a()])
+        );
+        assert_eq!(or([a(), b()]), Re::Class(CharClass::new([('a', 'b')])));
+        assert_eq!(and([a(), b()]), Re::Empty);
+        assert_eq!(and([ab.clone(), Re::anything()]), ab);
+        assert_eq!(not(not(ab.clone())), ab);
+        assert_eq!(star(star(a())), star(a()));
+        // Derivatives of a star stay finite
+        let re = star(ab.clone());
+        assert_eq!(re.derive('a').derive('b'), re);
+    }
+
+    #[test]
+    fn test_to_dfa() {
+        let dfa = derivatives("((a|b)*abb)").to_dfa();
+        let expected = Dfa::from_str("ab|*a.b.b.").unwrap();
+        assert!(dfa.is_equivalent(&expected).is_yes());
+
+        let dfa = derivatives("(.*a.*&~(.*b.*))").to_dfa();
+        assert!(dfa.test("xax"));
+        assert!(!dfa.test("ab"));
+        assert!(!dfa.test("x"));
+
+        let derivatives = derivatives("((a|b)*a(a|b)(a|b)(a|b))");
+        assert!(derivatives.to_bounded_dfa(8).is_none());
+        assert!(derivatives.to_bounded_dfa(16).is_some());
+    }
+
+    #[test]
+    fn test_unsupported() {
+        for expr in ["(^a)", r"((a)\1)", "((?=a)a)"] {
+            assert!(matches!(
+                Builder::new().build_derivatives(expr),
+                Err(CompileError::UnsupportedDerivative(_))
+            ));
+        }
+    }
+}
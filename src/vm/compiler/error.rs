@@ -8,4 +8,46 @@ pub enum ParseError {
     UnexpectedToken(Token),
     #[error("Unexpected EOF")]
     UnexpectedEOF,
+    #[error("Pattern nested deeper than {0} levels")]
+    NestingTooDeep(usize),
+    #[error("Unknown Unicode property: {0:?}")]
+    UnknownProperty(String),
+    #[error("Missing `}}` after Unicode property: {0:?}")]
+    UnclosedProperty(String),
+    #[error("Unknown POSIX class: {0:?}")]
+    UnknownClass(String),
+    #[error("Unsupported collating element: {0:?}")]
+    UnknownCollating(String),
+    #[error("Invalid range: {0}-{1}")]
+    InvalidRange(char, char),
+    #[error("Invalid interval: {{{0},{1}}}")]
+    InvalidInterval(usize, usize),
+    #[error("Interval count over {0}")]
+    IntervalTooLarge(usize),
+    #[error("Pattern spells out more than {0} terms")]
+    TooLarge(usize),
+    #[error("Invalid group name: {0:?}")]
+    InvalidGroupName(String),
+    #[error("Duplicate group name: {0}")]
+    DuplicateGroupName(String),
+}
+
+#[derive(Error, Debug)]
+pub enum CompileError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error(
+        "Backreference \\{0} needs the backtracking engine, compile it with `compile_backtracking`"
+    )]
+    UnsupportedBackref(usize),
+    #[error("Unbounded lookbehind needs the backtracking engine, compile it with `compile_backtracking`")]
+    UnboundedLookbehind,
+    #[error("Backreference \\{0} refers to a nonexistent group")]
+    InvalidBackref(usize),
+    #[error("Derivatives do not support {0}")]
+    UnsupportedDerivative(&'static str),
+    #[error("Reversed patterns do not support {0}")]
+    UnsupportedReverse(&'static str),
+    #[error("Program has more than {0} instructions")]
+    TooManyInsts(usize),
 }
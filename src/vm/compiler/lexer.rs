@@ -1,10 +1,22 @@
-use super::token::Token;
+use std::{cell::Cell, iter::Peekable, rc::Rc};
+
+use super::{ast::Syntax, token::Token};
 
 pub struct Lexer<T>
 where
     T: Iterator<Item = char>,
 {
-    iter: T,
+    iter: Peekable<T>,
+    syntax: Syntax,
+    bracket: Option<Bracket>,
+    verbose: Rc<Cell<bool>>,
+}
+
+/// Position inside a bracket expression such as `[^]a-z]`.
+#[derive(Clone, Copy)]
+struct Bracket {
+    read: usize,
+    negated: bool,
 }
 
 impl<T> Lexer<T>
@@ -12,7 +24,119 @@ where
     T: Iterator<Item = char>,
 {
     pub fn new(iter: T) -> Self {
-        Lexer { iter }
+        Lexer {
+            iter: iter.peekable(),
+            syntax: Syntax::default(),
+            bracket: None,
+            verbose: Rc::default(),
+        }
+    }
+
+    pub fn with_syntax(mut self, syntax: Syntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
+
+    /// Makes the lexer skip whitespace and `#` comments, as the parser says.
+    pub fn verbose(&self) -> Rc<Cell<bool>> {
+        self.verbose.clone()
+    }
+
+    /// Skips whitespace and `#` comments running to the end of the line.
+    fn skip_whitespace(&mut self) {
+        while let Some(&c) = self.iter.peek() {
+            if c == '#' {
+                self.iter.by_ref().find(|&c| c == '\n');
+            } else if c.is_whitespace() {
+                self.iter.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Token {
+        match self.iter.next() {
+            Some(c @ '1'..='9') => {
+                let mut n = c.to_digit(10).unwrap() as usize;
+                while let Some(d) = self.iter.peek().and_then(|c| c.to_digit(10)) {
+                    self.iter.next();
+                    n = n * 10 + d as usize;
+                }
+                Token::Backref(n)
+            }
+            Some(c @ ('p' | 'P')) => match self.property_name() {
+                Ok(name) => Token::Property {
+                    name,
+                    negate: c == 'P',
+                },
+                Err(name) => Token::UnclosedProperty(name),
+            },
+            Some('n') => Token::Literal('\n'),
+            Some('r') => Token::Literal('\r'),
+            Some('t') => Token::Literal('\t'),
+            Some(c) => Token::Literal(c),
+            // A trailing backslash stands for itself
+            None => Token::Literal('\\'),
+        }
+    }
+}
+
+impl<T> Lexer<T>
+where
+    T: Iterator<Item = char>,
+{
+    /// Inside a bracket expression.
+    fn next_in_bracket(&mut self, bracket: Bracket) -> Option<Token> {
+        let c = self.iter.next()?;
+        self.bracket = Some(Bracket {
+            read: bracket.read + 1,
+            negated: bracket.negated || (bracket.read == 0 && c == '^'),
+        });
+        let token = match c {
+            // A leading `]` stands for itself
+            ']' if bracket.read > usize::from(bracket.negated) => {
+                self.bracket = None;
+                Token::RightSquare
+            }
+            '^' if bracket.read == 0 => Token::Caret,
+            '[' if matches!(self.iter.peek(), Some(':' | '.' | '=')) => {
+                let kind = self.iter.next().unwrap();
+                let mut name = String::new();
+                while let Some(c) = self.iter.next() {
+                    if c == kind && self.iter.peek() == Some(&']') {
+                        self.iter.next();
+                        break;
+                    }
+                    name.push(c);
+                }
+                match kind {
+                    ':' => Token::PosixClass(name),
+                    _ => Token::Collating(name),
+                }
+            }
+            '\\' if self.syntax == Syntax::Default => self.escape(),
+            c => Token::Char(c),
+        };
+        Some(token)
+    }
+
+    /// `Err` with what follows `{` when `}` is missing.
+    fn property_name(&mut self) -> Result<String, String> {
+        match self.iter.next() {
+            Some('{') => {
+                let mut name = String::new();
+                loop {
+                    match self.iter.next() {
+                        Some('}') => return Ok(name),
+                        Some(c) => name.push(c),
+                        None => return Err(name),
+                    }
+                }
+            }
+            Some(c) => Ok(c.to_string()),
+            None => Ok(String::new()),
+        }
     }
 }
 
@@ -23,6 +147,12 @@ where
     type Item = Token;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if let Some(bracket) = self.bracket {
+            return self.next_in_bracket(bracket);
+        }
+        if self.verbose.get() {
+            self.skip_whitespace();
+        }
         match self.iter.next() {
             Some('|') => Some(Token::Alternate),
             Some('?') => Some(Token::ZeroOrOne),
@@ -30,6 +160,18 @@ where
             Some('+') => Some(Token::OneOrMore),
             Some('(') => Some(Token::LeftBracket),
             Some(')') => Some(Token::RightBracket),
+            Some('.') => Some(Token::Dot),
+            Some('^') => Some(Token::Caret),
+            Some('$') => Some(Token::Dollar),
+            Some('[') => {
+                self.bracket = Some(Bracket {
+                    read: 0,
+                    negated: false,
+                });
+                Some(Token::LeftSquare)
+            }
+            Some('{') if self.syntax == Syntax::PosixExtended => Some(Token::LeftCurly),
+            Some('\\') => Some(self.escape()),
             Some(c) => Some(Token::Char(c)),
             None => None,
         }
@@ -53,4 +195,77 @@ mod tests {
         assert_eq!(lexer.next(), Some(Token::ZeroOrOne));
         assert_eq!(lexer.next(), None);
     }
+
+    #[test]
+    fn test_bracket() {
+        let mut lexer = Lexer::new(r"[^]a-[:alpha:][.-.]\]]*".chars());
+        assert_eq!(lexer.next(), Some(Token::LeftSquare));
+        assert_eq!(lexer.next(), Some(Token::Caret));
+        assert_eq!(lexer.next(), Some(Token::Char(']')));
+        assert_eq!(lexer.next(), Some(Token::Char('a')));
+        assert_eq!(lexer.next(), Some(Token::Char('-')));
+        assert_eq!(lexer.next(), Some(Token::PosixClass("alpha".to_string())));
+        assert_eq!(lexer.next(), Some(Token::Collating("-".to_string())));
+        assert_eq!(lexer.next(), Some(Token::Literal(']')));
+        assert_eq!(lexer.next(), Some(Token::RightSquare));
+        assert_eq!(lexer.next(), Some(Token::ZeroOrMore));
+        assert_eq!(lexer.next(), None);
+
+        let mut lexer = Lexer::new(r"[\]]".chars()).with_syntax(Syntax::PosixExtended);
+        assert_eq!(lexer.next(), Some(Token::LeftSquare));
+        assert_eq!(lexer.next(), Some(Token::Char('\\')));
+        assert_eq!(lexer.next(), Some(Token::RightSquare));
+        assert_eq!(lexer.next(), Some(Token::Char(']')));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_verbose() {
+        let mut lexer = Lexer::new("a # [x\\\n \\  [ ]".chars());
+        lexer.verbose().set(true);
+        assert_eq!(lexer.next(), Some(Token::Char('a')));
+        assert_eq!(lexer.next(), Some(Token::Literal(' ')));
+        assert_eq!(lexer.next(), Some(Token::LeftSquare));
+        assert_eq!(lexer.next(), Some(Token::Char(' ')));
+        assert_eq!(lexer.next(), Some(Token::RightSquare));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_escape() {
+        let mut lexer = Lexer::new(r"\(\1\12a.\.\n\".chars());
+        assert_eq!(lexer.next(), Some(Token::Literal('(')));
+        assert_eq!(lexer.next(), Some(Token::Backref(1)));
+        assert_eq!(lexer.next(), Some(Token::Backref(12)));
+        assert_eq!(lexer.next(), Some(Token::Char('a')));
+        assert_eq!(lexer.next(), Some(Token::Dot));
+        assert_eq!(lexer.next(), Some(Token::Literal('.')));
+        assert_eq!(lexer.next(), Some(Token::Literal('\n')));
+        assert_eq!(lexer.next(), Some(Token::Literal('\\')));
+        assert_eq!(lexer.next(), None);
+
+        let mut lexer = Lexer::new(r"\pL\P{Greek}".chars());
+        assert_eq!(
+            lexer.next(),
+            Some(Token::Property {
+                name: "L".to_string(),
+                negate: false
+            })
+        );
+        assert_eq!(
+            lexer.next(),
+            Some(Token::Property {
+                name: "Greek".to_string(),
+                negate: true
+            })
+        );
+        assert_eq!(lexer.next(), None);
+
+        let mut lexer = Lexer::new(r"\p{Greek)".chars());
+        assert_eq!(
+            lexer.next(),
+            Some(Token::UnclosedProperty("Greek)".to_string()))
+        );
+        assert_eq!(lexer.next(), None);
+    }
 }
@@ -0,0 +1,311 @@
+use super::ast::{Expr, Factor, FactorConn, Flags, Group, GroupKind, Term};
+
+/// Literal text found in every match of a pattern.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Literals {
+    prefix: String,
+    suffix: String,
+    inner: String,
+}
+
+impl Literals {
+    pub(super) fn new(group: &Group, flags: Flags) -> Self {
+        let info = Analysis { flags }.group(group);
+        Self {
+            prefix: info.prefix,
+            suffix: info.suffix,
+            inner: info.inner,
+        }
+    }
+
+    /// Text every match starts with.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Text every match ends with.
+    pub fn suffix(&self) -> &str {
+        &self.suffix
+    }
+
+    /// Longest of the literals found in every match.
+    pub fn inner(&self) -> &str {
+        &self.inner
+    }
+}
+
+/// Literals of a pattern such as `(foo|bar|baz)`.
+pub(super) fn alternatives(group: &Group, flags: Flags) -> Option<Vec<String>> {
+    let [(kind, expr)] = group.0.as_slice() else {
+        return None;
+    };
+    let flags = match kind {
+        GroupKind::Capture { .. } => flags,
+        GroupKind::NonCapture(modifier) => modifier.apply(flags),
+        _ => return None,
+    };
+    if flags.case_insensitive || expr.0.len() < 2 {
+        return None;
+    }
+    expr.0
+        .iter()
+        .map(|conn| {
+            conn.0
+                .iter()
+                .map(|factor| match factor {
+                    Factor::Plain(Term::Char(c)) => Some(*c),
+                    _ => None,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// `exact` when the sub-pattern only matches that text.
+struct Info {
+    exact: Option<String>,
+    prefix: String,
+    suffix: String,
+    inner: String,
+}
+
+impl Info {
+    fn exact(text: String) -> Self {
+        Self {
+            exact: Some(text.clone()),
+            prefix: text.clone(),
+            suffix: text.clone(),
+            inner: text,
+        }
+    }
+
+    fn unknown() -> Self {
+        Self {
+            exact: None,
+            prefix: String::new(),
+            suffix: String::new(),
+            inner: String::new(),
+        }
+    }
+
+    fn catenation(self, rhs: Info) -> Info {
+        if let (Some(lhs), Some(rhs)) = (&self.exact, &rhs.exact) {
+            return Info::exact(format!("{lhs}{rhs}"));
+        }
+        let prefix = match &self.exact {
+            Some(lhs) => format!("{lhs}{}", rhs.prefix),
+            None => self.prefix,
+        };
+        let suffix = match &rhs.exact {
+            Some(rhs) => format!("{}{rhs}", self.suffix),
+            None => rhs.suffix,
+        };
+        // The end of the left side always touches the start of the right one
+        let junction = format!("{}{}", self.suffix, rhs.prefix);
+        // The last longest one wins, preferring the prefix
+        let inner = [
+            rhs.inner,
+            self.inner,
+            junction,
+            suffix.clone(),
+            prefix.clone(),
+        ]
+        .into_iter()
+        .max_by_key(String::len)
+        .unwrap();
+        Info {
+            exact: None,
+            prefix,
+            suffix,
+            inner,
+        }
+    }
+
+    fn alternation(mut alternatives: Vec<Info>) -> Info {
+        if alternatives.len() == 1 {
+            return alternatives.pop().unwrap();
+        }
+        let exact = &alternatives[0].exact;
+        if exact.is_some() && alternatives.iter().all(|info| &info.exact == exact) {
+            return Info::exact(exact.clone().unwrap());
+        }
+        let prefix = alternatives
+            .iter()
+            .map(|info| info.prefix.as_str())
+            .reduce(common_prefix)
+            .unwrap()
+            .to_string();
+        let suffix = alternatives
+            .iter()
+            .map(|info| info.suffix.as_str())
+            .reduce(common_suffix)
+            .unwrap()
+            .to_string();
+        let inner = if prefix.len() >= suffix.len() {
+            prefix.clone()
+        } else {
+            suffix.clone()
+        };
+        Info {
+            exact: None,
+            prefix,
+            suffix,
+            inner,
+        }
+    }
+}
+
+fn common_prefix<'a>(a: &'a str, b: &str) -> &'a str {
+    let len = a
+        .char_indices()
+        .zip(b.chars())
+        .find(|((_, c1), c2)| c1 != c2)
+        .map_or(a.len().min(b.len()), |((i, _), _)| i);
+    &a[..len]
+}
+
+fn common_suffix<'a>(a: &'a str, b: &str) -> &'a str {
+    let len = a
+        .chars()
+        .rev()
+        .zip(b.chars().rev())
+        .take_while(|(c1, c2)| c1 == c2)
+        .map(|(c, _)| c.len_utf8())
+        .sum::<usize>();
+    &a[a.len() - len..]
+}
+
+/// Walks the syntax tree with the flags in effect, like the transformer.
+struct Analysis {
+    flags: Flags,
+}
+
+impl Analysis {
+    fn group(&mut self, group: &Group) -> Info {
+        let mut info = Info::exact(String::new());
+        for (kind, expr) in &group.0 {
+            let flags = self.flags;
+            let next = match kind {
+                GroupKind::Flags(modifier) => {
+                    self.flags = modifier.apply(flags);
+                    continue;
+                }
+                GroupKind::Capture { .. } => self.expr(expr),
+                GroupKind::NonCapture(modifier) => {
+                    self.flags = modifier.apply(flags);
+                    self.expr(expr)
+                }
+                // Lookarounds match no text of their own
+                GroupKind::LookAhead { .. } | GroupKind::LookBehind { .. } => {
+                    Info::exact(String::new())
+                }
+            };
+            self.flags = flags;
+            info = info.catenation(next);
+        }
+        // Flags set by `(?i)` stay in effect after it, as in the transformer
+        info
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Info {
+        Info::alternation(expr.0.iter().map(|conn| self.factor_conn(conn)).collect())
+    }
+
+    fn factor_conn(&mut self, conn: &FactorConn) -> Info {
+        conn.0
+            .iter()
+            .fold(Info::exact(String::new()), |info, factor| {
+                info.catenation(self.factor(factor))
+            })
+    }
+
+    fn factor(&mut self, factor: &Factor) -> Info {
+        match factor {
+            Factor::Plain(term) => self.term(term),
+            Factor::ZeroOrOne(..) | Factor::ZeroOrMore(..) => Info::unknown(),
+            Factor::OneOrMore(term, _) => Info {
+                exact: None,
+                ..self.term(term)
+            },
+        }
+    }
+
+    fn term(&mut self, term: &Term) -> Info {
+        match term {
+            Term::Char(c) if !self.flags.case_insensitive => Info::exact(c.to_string()),
+            Term::Caret | Term::Dollar => Info::exact(String::new()),
+            Term::Group(group) => self.group(group),
+            // Every match of a conjunction matches each of its operands
+            Term::And(exprs) => exprs
+                .iter()
+                .map(|expr| self.expr(expr))
+                .max_by_key(|info| info.inner.len())
+                .unwrap_or_else(Info::unknown),
+            Term::Char(_) | Term::Class(..) | Term::Dot | Term::Backref(_) | Term::Not(_) => {
+                Info::unknown()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{lexer::Lexer, parser::Parser};
+
+    use super::*;
+
+    fn literals(expr: &str) -> (String, String, String) {
+        let mut lexer = Lexer::new(expr.chars());
+        let group = Parser::new(&mut lexer)
+            .with_boolean_ops(true)
+            .parse()
+            .unwrap();
+        let literals = Literals::new(&group, Flags::default());
+        (literals.prefix, literals.suffix, literals.inner)
+    }
+
+    fn owned(prefix: &str, suffix: &str, inner: &str) -> (String, String, String) {
+        (prefix.to_string(), suffix.to_string(), inner.to_string())
+    }
+
+    #[test]
+    fn test_alternatives() {
+        let alternatives = |expr: &str| {
+            let mut lexer = Lexer::new(expr.chars());
+            let group = Parser::new(&mut lexer).parse().unwrap();
+            alternatives(&group, Flags::default())
+        };
+        assert_eq!(
+            alternatives("(foo|bar|baz)"),
+            Some(vec![
+                "foo".to_string(),
+                "bar".to_string(),
+                "baz".to_string()
+            ])
+        );
+        assert_eq!(
+            alternatives("(?:a|λ)"),
+            Some(vec!["a".to_string(), "λ".to_string()])
+        );
+        assert_eq!(alternatives("(foo)"), None);
+        assert_eq!(alternatives("(foo|ba+r)"), None);
+        assert_eq!(alternatives("(?i:foo|bar)"), None);
+        assert_eq!(alternatives("(foo|bar)(baz)"), None);
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(literals("(abc)"), owned("abc", "abc", "abc"));
+        assert_eq!(literals("(ab[0-9]+cd)"), owned("ab", "cd", "ab"));
+        assert_eq!(literals("(x*ERROR: .*)"), owned("", "", "ERROR: "));
+        assert_eq!(literals("(a(b|c)d)"), owned("a", "d", "a"));
+        assert_eq!(literals("(foo|foobar)"), owned("foo", "", "foo"));
+        assert_eq!(literals("(^(?:ab)+c$)"), owned("ab", "abc", "abc"));
+        assert_eq!(literals("(.(?=xyz)q)"), owned("", "q", "q"));
+        assert_eq!(literals("(a?)"), owned("", "", ""));
+        assert_eq!(literals("(x(?i)abc)"), owned("x", "", "x"));
+        assert_eq!(literals("(.*key.*&.*value.*)"), owned("", "", "value"));
+        // Junction of a suffix and a prefix
+        assert_eq!(literals("((a|ba)(cd|ce))"), owned("", "", "ac"));
+    }
+}
@@ -1,18 +1,30 @@
-use super::inst::{self, Inst};
+use super::{inst::Inst, program::Program};
 
 mod ast;
+mod builder;
+mod derivative;
 mod error;
 mod lexer;
+mod literal;
 mod parser;
+mod reverse;
 mod token;
 mod transformer;
 
-pub fn compile(expr: &str) -> Vec<Inst> {
-    let mut lexer = lexer::Lexer::new(expr.chars());
-    let mut parser = parser::Parser::new(&mut lexer);
-    let mut transformer = transformer::Transformer::default();
-    let ast = parser.parse_group().unwrap();
-    transformer.transform(ast)
+pub use ast::Syntax;
+pub use builder::Builder;
+pub use derivative::Derivatives;
+pub use error::{CompileError, ParseError};
+pub use literal::Literals;
+
+/// Rejects backreferences and unbounded lookbehinds, see [`compile_backtracking`].
+pub fn compile(expr: &str) -> Result<Program, CompileError> {
+    Builder::new().build(expr)
+}
+
+/// Allows backreferences and unbounded lookbehinds.
+pub fn compile_backtracking(expr: &str) -> Result<Program, CompileError> {
+    Builder::new().backtracking(true).build(expr)
 }
 
 pub fn print_prog(insts: &[Inst]) {
@@ -20,3 +32,81 @@ pub fn print_prog(insts: &[Inst]) {
         println!("{}: {}", i, inst);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile_backref() {
+        assert!(matches!(
+            compile(r"((a|b)\2)"),
+            Err(CompileError::UnsupportedBackref(2))
+        ));
+        assert!(compile_backtracking(r"((a|b)\2)").is_ok());
+        assert!(matches!(
+            compile_backtracking(r"((a|b)\3)"),
+            Err(CompileError::InvalidBackref(3))
+        ));
+        assert!(matches!(
+            compile("((a|b)"),
+            Err(CompileError::Parse(ParseError::UnexpectedEOF))
+        ));
+    }
+
+    #[test]
+    fn test_compile_captures() {
+        let prog = compile("((?:a)(?P<first>b)((?<second>c)d))").unwrap();
+        assert_eq!(prog.captures_len(), 5);
+        assert_eq!(
+            prog.capture_names().collect::<Vec<_>>(),
+            [None, None, Some("first"), None, Some("second")]
+        );
+        assert_eq!(prog.capture_index("second"), Some(4));
+        assert_eq!(prog.capture_index("third"), None);
+    }
+
+    #[test]
+    fn test_compile_lookbehind() {
+        assert!(compile("((?<=a(b|cd)?)e)").is_ok());
+        assert!(matches!(
+            compile("((?<=ab*)c)"),
+            Err(CompileError::UnboundedLookbehind)
+        ));
+        assert!(compile_backtracking("((?<=ab*)c)").is_ok());
+    }
+
+    #[test]
+    fn test_compile_limits() {
+        let deep = format!("{}a{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert!(matches!(
+            compile(&deep),
+            Err(CompileError::Parse(ParseError::NestingTooDeep(128)))
+        ));
+        assert!(compile(&format!("{}a{}", "(".repeat(100), ")".repeat(100))).is_ok());
+        assert!(matches!(
+            Builder::new().nest_limit(2).build("(((a)))"),
+            Err(CompileError::Parse(ParseError::NestingTooDeep(2)))
+        ));
+        assert!(matches!(
+            Builder::new().nest_limit(2).build_derivatives("(~~a)"),
+            Err(CompileError::Parse(ParseError::NestingTooDeep(2)))
+        ));
+
+        assert!(Builder::new().max_insts(9).build("(abc)").is_ok());
+        assert!(matches!(
+            Builder::new().max_insts(9).build("(abc(?=de))"),
+            Err(CompileError::TooManyInsts(9))
+        ));
+        // Nested intervals fail before they are spelled out
+        let mut posix = Builder::new();
+        posix.syntax(Syntax::PosixExtended).max_insts(1000);
+        for expr in ["((a{100}){100}){100}", "((a{1000}){1000}){1000}"] {
+            assert!(matches!(
+                posix.build(expr),
+                Err(CompileError::Parse(ParseError::TooLarge(1000)))
+            ));
+        }
+        assert!(posix.build("(a{10}){10}").is_ok());
+    }
+}
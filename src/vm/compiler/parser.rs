@@ -1,4 +1,6 @@
-use std::iter::Peekable;
+use std::{cell::Cell, iter::Peekable, rc::Rc};
+
+use crate::class::CharClass;
 
 use super::{ast::*, error::ParseError, token::Token};
 
@@ -7,8 +9,29 @@ where
     T: Iterator<Item = Token>,
 {
     tokens: Peekable<T>,
+    capture_names: Vec<Option<String>>,
+    flags: Flags,
+    /// Verbose mode switch of the lexer, kept in sync with `flags`.
+    verbose: Rc<Cell<bool>>,
+    syntax: Syntax,
+    boolean_ops: bool,
+    /// Groups and complements open at the current token.
+    depth: usize,
+    nest_limit: usize,
+    /// Non-group terms parsed so far, intervals spelled out.
+    size: usize,
+    max_size: usize,
 }
 
+/// Fits parsing and compiling in the 2 MiB stack of a spawned debug thread.
+pub(super) const DEFAULT_NEST_LIMIT: usize = 128;
+
+/// Largest count of an interval, which is spelled out as copies of its term.
+const MAX_INTERVAL_COUNT: usize = 1000;
+
+/// Most terms a pattern may spell out when no other limit is set.
+pub(super) const DEFAULT_MAX_SIZE: usize = 1 << 16;
+
 impl<T> Parser<T>
 where
     T: Iterator<Item = Token>,
@@ -16,7 +39,84 @@ where
     pub fn new(tokens: T) -> Self {
         Parser {
             tokens: tokens.peekable(),
+            // Group 0 is the implicit whole match
+            capture_names: vec![None],
+            flags: Flags::default(),
+            verbose: Rc::default(),
+            syntax: Syntax::default(),
+            boolean_ops: false,
+            depth: 0,
+            nest_limit: DEFAULT_NEST_LIMIT,
+            size: 0,
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+
+    /// Rejects groups or complements nested deeper than `limit`.
+    pub fn with_nest_limit(mut self, limit: usize) -> Self {
+        self.nest_limit = limit;
+        self
+    }
+
+    fn enter(&mut self) -> ParseResult<()> {
+        if self.depth >= self.nest_limit {
+            return Err(ParseError::NestingTooDeep(self.nest_limit));
         }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Rejects patterns spelling out more than `limit` terms, so that nested
+    /// intervals fail before they are copied.
+    pub fn with_max_size(mut self, limit: usize) -> Self {
+        self.max_size = limit;
+        self
+    }
+
+    fn grow(&mut self, size: usize) -> ParseResult<()> {
+        self.size = self.size.saturating_add(size);
+        if self.size > self.max_size {
+            return Err(ParseError::TooLarge(self.max_size));
+        }
+        Ok(())
+    }
+
+    pub fn with_syntax(mut self, syntax: Syntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
+
+    /// Parses `&` and `~`, which only derivatives can evaluate.
+    pub fn with_boolean_ops(mut self, yes: bool) -> Self {
+        self.boolean_ops = yes;
+        self
+    }
+
+    /// Sets the flags in effect before any flag group.
+    pub fn with_flags(mut self, flags: Flags) -> Self {
+        self.set_flags(flags);
+        self
+    }
+
+    /// Verbose mode switch of the lexer, see `Lexer::verbose`.
+    pub fn with_verbose(mut self, verbose: Rc<Cell<bool>>) -> Self {
+        self.verbose = verbose;
+        self.set_flags(self.flags);
+        self
+    }
+
+    fn set_flags(&mut self, flags: Flags) {
+        self.flags = flags;
+        self.verbose.set(flags.verbose);
+    }
+
+    /// Names of the capture groups parsed so far, indexed by group.
+    pub fn capture_names(&self) -> &[Option<String>] {
+        &self.capture_names
+    }
+
+    fn peek(&mut self) -> Option<&Token> {
+        self.tokens.peek()
     }
 
     fn advance(&mut self) -> ParseResult<Token> {
@@ -40,30 +140,176 @@ impl<T> Parser<T>
 where
     T: Iterator<Item = Token>,
 {
+    /// POSIX patterns are bare expressions rather than a sequence of groups.
+    pub fn parse(&mut self) -> ParseResult<Group> {
+        match self.syntax {
+            Syntax::Default => self.parse_group(),
+            Syntax::PosixExtended => {
+                let expr = self.parse_expr()?;
+                match self.advance() {
+                    Ok(token) => Err(ParseError::UnexpectedToken(token)),
+                    Err(_) => Ok(Group(vec![(
+                        GroupKind::NonCapture(FlagsModifier::default()),
+                        expr,
+                    )])),
+                }
+            }
+        }
+    }
+
     pub fn parse_group(&mut self) -> ParseResult<Group> {
         let mut exprs = vec![];
-        while let Some(Token::LeftBracket) = self.tokens.peek() {
+        while let Some(Token::LeftBracket) = self.peek() {
             self.expected(Token::LeftBracket)?;
-            exprs.push(self.parse_expr()?);
+            self.enter()?;
+            let flags = self.flags;
+            let kind = self.parse_group_kind()?;
+            let expr = match kind {
+                GroupKind::Flags(modifier) => {
+                    self.set_flags(modifier.apply(flags));
+                    Expr(vec![])
+                }
+                GroupKind::NonCapture(modifier) => {
+                    self.set_flags(modifier.apply(flags));
+                    let expr = self.parse_expr()?;
+                    self.set_flags(flags);
+                    expr
+                }
+                _ => {
+                    let expr = self.parse_expr()?;
+                    self.set_flags(flags);
+                    expr
+                }
+            };
             self.expected(Token::RightBracket)?;
+            self.depth -= 1;
+            exprs.push((kind, expr));
         }
         Ok(Group(exprs))
     }
 
+    /// Capture groups are numbered by the position of their left bracket.
+    pub fn parse_group_kind(&mut self) -> ParseResult<GroupKind> {
+        if self.syntax != Syntax::Default || self.tokens.peek() != Some(&Token::ZeroOrOne) {
+            return Ok(self.add_capture(None));
+        }
+        self.advance()?;
+        match self.advance()? {
+            Token::Char(':') => Ok(GroupKind::NonCapture(FlagsModifier::default())),
+            Token::Char('=') => Ok(GroupKind::LookAhead { negate: false }),
+            Token::Char('!') => Ok(GroupKind::LookAhead { negate: true }),
+            Token::Char('<') => match self.tokens.peek() {
+                Some(Token::Char('=')) => {
+                    self.advance()?;
+                    Ok(GroupKind::LookBehind { negate: false })
+                }
+                Some(Token::Char('!')) => {
+                    self.advance()?;
+                    Ok(GroupKind::LookBehind { negate: true })
+                }
+                _ => self.parse_group_name(),
+            },
+            Token::Char('P') => match self.advance()? {
+                Token::Char('<') => self.parse_group_name(),
+                token => Err(ParseError::UnexpectedToken(token)),
+            },
+            Token::Char(c) if "imsx-".contains(c) => self.parse_flags(c),
+            token => Err(ParseError::UnexpectedToken(token)),
+        }
+    }
+
+    /// Parses flags such as `i-sx` up to a `:` or, left in place, a `)`.
+    fn parse_flags(&mut self, first: char) -> ParseResult<GroupKind> {
+        let mut modifier = FlagsModifier::default();
+        let mut negate = false;
+        let mut c = first;
+        loop {
+            let flags = if negate {
+                &mut modifier.off
+            } else {
+                &mut modifier.on
+            };
+            match c {
+                'i' => flags.case_insensitive = true,
+                'm' => flags.multi_line = true,
+                's' => flags.dot_all = true,
+                'x' => flags.verbose = true,
+                '-' if !negate => negate = true,
+                _ => return Err(ParseError::UnexpectedToken(Token::Char(c))),
+            }
+            match self.tokens.peek() {
+                Some(Token::RightBracket) => return Ok(GroupKind::Flags(modifier)),
+                Some(Token::Char(':')) => {
+                    self.advance()?;
+                    return Ok(GroupKind::NonCapture(modifier));
+                }
+                _ => match self.advance()? {
+                    Token::Char(c1) => c = c1,
+                    token => return Err(ParseError::UnexpectedToken(token)),
+                },
+            }
+        }
+    }
+
+    /// Parses a group name up to and including the closing `>`.
+    fn parse_group_name(&mut self) -> ParseResult<GroupKind> {
+        let mut name = String::new();
+        loop {
+            match self.advance()? {
+                Token::Char('>') => break,
+                Token::Char(c) if c.is_alphanumeric() || c == '_' => name.push(c),
+                token => return Err(ParseError::UnexpectedToken(token)),
+            }
+        }
+        if name.is_empty() || name.starts_with(|c: char| c.is_numeric()) {
+            return Err(ParseError::InvalidGroupName(name));
+        }
+        if self.capture_names.contains(&Some(name.clone())) {
+            return Err(ParseError::DuplicateGroupName(name));
+        }
+        Ok(self.add_capture(Some(name)))
+    }
+
+    fn add_capture(&mut self, name: Option<String>) -> GroupKind {
+        let index = self.capture_names.len();
+        self.capture_names.push(name.clone());
+        GroupKind::Capture { index, name }
+    }
+
     pub fn parse_expr(&mut self) -> ParseResult<Expr> {
-        let mut factor_conns = vec![self.parse_factor_conn()?];
-        while let Some(Token::Alternate) = self.tokens.peek() {
+        let mut factor_conns = vec![self.parse_conjunction()?];
+        while let Some(Token::Alternate) = self.peek() {
             self.advance()?;
-            let next_factor_conn = self.parse_factor_conn()?;
+            let next_factor_conn = self.parse_conjunction()?;
             factor_conns.push(next_factor_conn);
         }
         Ok(Expr(factor_conns))
     }
 
+    /// Binds tighter than `|` but looser than concatenation.
+    fn parse_conjunction(&mut self) -> ParseResult<FactorConn> {
+        let first = self.parse_factor_conn()?;
+        if !self.at_and() {
+            return Ok(first);
+        }
+        let mut exprs = vec![Expr(vec![first])];
+        while self.at_and() {
+            self.advance()?;
+            exprs.push(Expr(vec![self.parse_factor_conn()?]));
+        }
+        Ok(FactorConn(vec![Factor::Plain(Term::And(exprs))]))
+    }
+
+    fn at_and(&mut self) -> bool {
+        self.boolean_ops && self.peek() == Some(&Token::Char('&'))
+    }
+
     pub fn parse_factor_conn(&mut self) -> ParseResult<FactorConn> {
         let mut factors = vec![self.parse_factor()?];
-        while let Some(&token) = self.tokens.peek() {
-            if token != Token::Alternate && token != Token::RightBracket {
+        let boolean_ops = self.boolean_ops;
+        while let Some(token) = self.peek() {
+            let and = boolean_ops && *token == Token::Char('&');
+            if *token != Token::Alternate && *token != Token::RightBracket && !and {
                 let next_factor = self.parse_factor()?;
                 factors.push(next_factor);
             } else {
@@ -74,43 +320,189 @@ where
     }
 
     pub fn parse_factor(&mut self) -> ParseResult<Factor> {
+        // `~` complements the whole quantified term following it
+        if self.boolean_ops && self.peek() == Some(&Token::Char('~')) {
+            self.advance()?;
+            self.enter()?;
+            let factor = self.parse_factor()?;
+            self.depth -= 1;
+            return Ok(Factor::Plain(Term::Not(Box::new(factor))));
+        }
+        let start = self.size;
         let term = self.parse_term()?;
-        match self.tokens.peek() {
-            Some(token) => match token {
-                Token::ZeroOrOne => {
-                    self.advance()?;
-                    Ok(Factor::ZeroOrOne(term))
+        if !matches!(term, Term::Group(_)) {
+            self.grow(1)?;
+        }
+        let factor = match self.peek() {
+            Some(Token::LeftCurly) => return self.parse_interval(term, self.size - start),
+            Some(Token::ZeroOrOne) => Factor::ZeroOrOne,
+            Some(Token::ZeroOrMore) => Factor::ZeroOrMore,
+            Some(Token::OneOrMore) => Factor::OneOrMore,
+            _ => return Ok(Factor::Plain(term)),
+        };
+        self.advance()?;
+        // POSIX has no lazy quantifiers, the extra `?` is rejected as a term
+        let lazy = self.syntax == Syntax::Default && self.peek() == Some(&Token::ZeroOrOne);
+        if lazy {
+            self.advance()?;
+        }
+        Ok(factor(term, lazy))
+    }
+
+    /// `{m}`, `{m,}` or `{m,n}`, spelled out as copies of `term`. A missing `m`
+    /// stands for 0 as in GNU `grep -E`.
+    fn parse_interval(&mut self, term: Term, size: usize) -> ParseResult<Factor> {
+        self.expected(Token::LeftCurly)?;
+        let min = self.parse_count()?;
+        let (min, max) = match self.advance()? {
+            Token::Char('}') => {
+                let min = min.ok_or(ParseError::UnexpectedToken(Token::Char('}')))?;
+                (min, Some(min))
+            }
+            Token::Char(',') => {
+                let max = self.parse_count()?;
+                self.expected(Token::Char('}'))?;
+                (min.unwrap_or(0), max)
+            }
+            token => return Err(ParseError::UnexpectedToken(token)),
+        };
+        if let Some(max) = max.filter(|&max| max < min) {
+            return Err(ParseError::InvalidInterval(min, max));
+        }
+        self.size -= size;
+        self.grow(size.saturating_mul(max.unwrap_or(min + 1)))?;
+        let mut factors = vec![Factor::Plain(term.clone()); min];
+        match max {
+            Some(max) => factors.extend(vec![Factor::ZeroOrOne(term, false); max - min]),
+            None => factors.push(Factor::ZeroOrMore(term, false)),
+        }
+        let expr = Expr(vec![FactorConn(factors)]);
+        let group = Group(vec![(
+            GroupKind::NonCapture(FlagsModifier::default()),
+            expr,
+        )]);
+        Ok(Factor::Plain(Term::Group(group)))
+    }
+
+    /// Parses the decimal count of an interval, if any.
+    fn parse_count(&mut self) -> ParseResult<Option<usize>> {
+        let mut count = None;
+        while let Some(&Token::Char(c)) = self.peek() {
+            let Some(digit) = c.to_digit(10) else {
+                break;
+            };
+            self.advance()?;
+            let n = count.unwrap_or(0) * 10 + digit as usize;
+            if n > MAX_INTERVAL_COUNT {
+                return Err(ParseError::IntervalTooLarge(MAX_INTERVAL_COUNT));
+            }
+            count = Some(n);
+        }
+        Ok(count)
+    }
+
+    /// Parses a bracket expression such as `[^a-z[:digit:]]`.
+    pub fn parse_bracket(&mut self) -> ParseResult<Term> {
+        self.expected(Token::LeftSquare)?;
+        let negate = self.tokens.peek() == Some(&Token::Caret);
+        if negate {
+            self.advance()?;
+        }
+        let mut ranges = vec![];
+        let mut classes = vec![];
+        loop {
+            let start = match self.advance()? {
+                Token::RightSquare => break,
+                Token::Char(c) | Token::Literal(c) => c,
+                Token::Collating(name) => single_char(name)?,
+                Token::PosixClass(name) => {
+                    classes.push(CharClass::posix(&name).ok_or(ParseError::UnknownClass(name))?);
+                    continue;
                 }
-                Token::ZeroOrMore => {
-                    self.advance()?;
-                    Ok(Factor::ZeroOrMore(term))
+                Token::Property { name, negate } => {
+                    let class = CharClass::unicode_property(&name)
+                        .ok_or(ParseError::UnknownProperty(name))?;
+                    classes.push(if negate { class.negate() } else { class });
+                    continue;
                 }
-                Token::OneOrMore => {
-                    self.advance()?;
-                    Ok(Factor::OneOrMore(term))
+                Token::UnclosedProperty(name) => return Err(ParseError::UnclosedProperty(name)),
+                token => return Err(ParseError::UnexpectedToken(token)),
+            };
+            if self.tokens.peek() != Some(&Token::Char('-')) {
+                ranges.push((start, start));
+                continue;
+            }
+            self.advance()?;
+            let end = match self.advance()? {
+                // A trailing `-` stands for itself
+                Token::RightSquare => {
+                    ranges.extend([(start, start), ('-', '-')]);
+                    break;
                 }
-                _ => Ok(Factor::Plain(term)),
-            },
-            None => Ok(Factor::Plain(term)),
+                Token::Char(c) | Token::Literal(c) => c,
+                Token::Collating(name) => single_char(name)?,
+                token => return Err(ParseError::UnexpectedToken(token)),
+            };
+            if end < start {
+                return Err(ParseError::InvalidRange(start, end));
+            }
+            ranges.push((start, end));
         }
+        let class = classes
+            .iter()
+            .fold(CharClass::new(ranges), |class, class1| class.union(class1));
+        Ok(Term::Class(class, negate))
     }
 
     pub fn parse_term(&mut self) -> ParseResult<Term> {
-        let token = *self.tokens.peek().ok_or(ParseError::UnexpectedEOF)?;
+        let token = self.peek().cloned().ok_or(ParseError::UnexpectedEOF)?;
         match token {
             Token::LeftBracket => {
                 let group = self.parse_group()?;
                 Ok(Term::Group(group))
             }
-            Token::Char(c) => {
+            Token::Char(c) | Token::Literal(c) => {
                 self.advance()?;
                 Ok(Term::Char(c))
             }
+            Token::LeftSquare => self.parse_bracket(),
+            Token::Property { name, negate } if self.syntax == Syntax::Default => {
+                self.advance()?;
+                let class =
+                    CharClass::unicode_property(&name).ok_or(ParseError::UnknownProperty(name))?;
+                Ok(Term::Class(class, negate))
+            }
+            Token::UnclosedProperty(name) => Err(ParseError::UnclosedProperty(name)),
+            Token::Dot => {
+                self.advance()?;
+                Ok(Term::Dot)
+            }
+            Token::Caret => {
+                self.advance()?;
+                Ok(Term::Caret)
+            }
+            Token::Dollar => {
+                self.advance()?;
+                Ok(Term::Dollar)
+            }
+            Token::Backref(n) => {
+                self.advance()?;
+                Ok(Term::Backref(n))
+            }
             _ => Err(ParseError::UnexpectedToken(token)),
         }
     }
 }
 
+/// Collating elements are limited to single chars, as in `[.-.]`.
+fn single_char(name: String) -> ParseResult<char> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(ParseError::UnknownCollating(name)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::vm::compiler::lexer::Lexer;
@@ -125,6 +517,218 @@ mod tests {
         assert!(program.is_ok())
     }
 
+    #[test]
+    fn test_lookaround() {
+        let lexer = Lexer::new("((?<=a)b(?!c))".chars());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_group();
+        assert!(program.is_ok());
+
+        let lexer = Lexer::new("((?<=)b)".chars());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_group();
+        assert!(matches!(
+            program,
+            Err(ParseError::UnexpectedToken(Token::RightBracket))
+        ));
+
+        let lexer = Lexer::new("((?<=a|b)".chars());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_group();
+        assert!(matches!(program, Err(ParseError::UnexpectedEOF)));
+    }
+
+    #[test]
+    fn test_group_kind() {
+        let lexer = Lexer::new("((?:a)(?P<first>b)((?<second>c)d))".chars());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_group();
+        assert!(program.is_ok());
+        assert_eq!(
+            parser.capture_names(),
+            [
+                None,
+                None,
+                Some("first".to_string()),
+                None,
+                Some("second".to_string())
+            ]
+        );
+
+        let lexer = Lexer::new("((?<a>b)(?P<a>c))".chars());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_group();
+        assert!(matches!(program, Err(ParseError::DuplicateGroupName(name)) if name == "a"));
+
+        let lexer = Lexer::new("((?<1a>b))".chars());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_group();
+        assert!(matches!(program, Err(ParseError::InvalidGroupName(name)) if name == "1a"));
+    }
+
+    #[test]
+    fn test_flags() {
+        let lexer = Lexer::new("((?i)a(?-i:b)(?sx: c  # comment\n d))".chars());
+        let verbose = lexer.verbose();
+        let mut parser = Parser::new(lexer).with_verbose(verbose);
+        let program = parser.parse_group();
+        assert!(program.is_ok());
+        assert_eq!(parser.capture_names().len(), 2);
+
+        let lexer = Lexer::new("((?ij)a)".chars());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_group();
+        assert!(matches!(
+            program,
+            Err(ParseError::UnexpectedToken(Token::Char('j')))
+        ));
+
+        let lexer = Lexer::new("( a b )".chars());
+        let verbose = lexer.verbose();
+        let mut parser = Parser::new(lexer).with_verbose(verbose).with_flags(Flags {
+            verbose: true,
+            ..Flags::default()
+        });
+        let program = parser.parse_group();
+        assert!(program.is_ok());
+    }
+
+    #[test]
+    fn test_property() {
+        let lexer = Lexer::new(r"(\p{Greek}+\PN)".chars());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_group();
+        assert!(program.is_ok());
+
+        let lexer = Lexer::new(r"(\p{Klingon})".chars());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_group();
+        assert!(matches!(program, Err(ParseError::UnknownProperty(name)) if name == "Klingon"));
+
+        for expr in [r"(\p{Greek)", r"([\p{Greek])"] {
+            let mut parser = Parser::new(Lexer::new(expr.chars()));
+            let program = parser.parse_group();
+            assert!(
+                matches!(program, Err(ParseError::UnclosedProperty(_))),
+                "{expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_bracket() {
+        let lexer = Lexer::new(r"([]a-c[:digit:]\p{Greek}-]+[^-x])".chars());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_group();
+        assert!(program.is_ok());
+
+        let lexer = Lexer::new(r"([z-a])".chars());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_group();
+        assert!(matches!(program, Err(ParseError::InvalidRange('z', 'a'))));
+
+        let lexer = Lexer::new(r"([[:alfa:]])".chars());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_group();
+        assert!(matches!(program, Err(ParseError::UnknownClass(name)) if name == "alfa"));
+
+        let lexer = Lexer::new(r"([[.ch.]])".chars());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_group();
+        assert!(matches!(program, Err(ParseError::UnknownCollating(name)) if name == "ch"));
+    }
+
+    #[test]
+    fn test_posix_syntax() {
+        let lexer = Lexer::new(r"a(b|c)*[[:digit:]]$".chars()).with_syntax(Syntax::PosixExtended);
+        let mut parser = Parser::new(lexer).with_syntax(Syntax::PosixExtended);
+        let program = parser.parse();
+        assert!(program.is_ok());
+        assert_eq!(parser.capture_names().len(), 2);
+
+        let lexer = Lexer::new(r"a*?".chars()).with_syntax(Syntax::PosixExtended);
+        let mut parser = Parser::new(lexer).with_syntax(Syntax::PosixExtended);
+        let program = parser.parse();
+        assert!(matches!(
+            program,
+            Err(ParseError::UnexpectedToken(Token::ZeroOrOne))
+        ));
+
+        let lexer = Lexer::new(r"(?:a)".chars()).with_syntax(Syntax::PosixExtended);
+        let mut parser = Parser::new(lexer).with_syntax(Syntax::PosixExtended);
+        let program = parser.parse();
+        assert!(matches!(
+            program,
+            Err(ParseError::UnexpectedToken(Token::ZeroOrOne))
+        ));
+
+        let parse = |expr: &str| {
+            let lexer = Lexer::new(expr.chars()).with_syntax(Syntax::PosixExtended);
+            Parser::new(lexer)
+                .with_syntax(Syntax::PosixExtended)
+                .parse()
+        };
+        for expr in ["a{2}", "a{2,}", "(ab){,3}", "a{0}b{0,0}", "x{1,2}}"] {
+            assert!(parse(expr).is_ok(), "{expr}");
+        }
+        assert!(matches!(
+            parse("a{3,2}"),
+            Err(ParseError::InvalidInterval(3, 2))
+        ));
+        assert!(matches!(
+            parse("a{1001}"),
+            Err(ParseError::IntervalTooLarge(1000))
+        ));
+        for expr in [
+            "((a{100}){100}){100}",
+            "((a{1000}){1000}){1000}",
+            "(a{300}b){300}",
+        ] {
+            assert!(
+                matches!(parse(expr), Err(ParseError::TooLarge(DEFAULT_MAX_SIZE))),
+                "{expr}"
+            );
+        }
+        for expr in ["a{}", "a{x}", "a{1", "{1}", "a{1,2,3}"] {
+            assert!(parse(expr).is_err(), "{expr}");
+        }
+    }
+
+    #[test]
+    fn test_boolean_ops() {
+        let lexer = Lexer::new(r"(a|b~c*&d\&)".chars());
+        let mut parser = Parser::new(lexer).with_boolean_ops(true);
+        let Ok(Group(groups)) = parser.parse_group() else {
+            panic!("expected a group")
+        };
+        let Expr(alternatives) = &groups[0].1;
+        assert_eq!(alternatives.len(), 2);
+        let FactorConn(factors) = &alternatives[1];
+        let [Factor::Plain(Term::And(exprs))] = &factors[..] else {
+            panic!("expected an intersection")
+        };
+        assert_eq!(exprs.len(), 2);
+        assert!(matches!(
+            &exprs[0].0[0].0[1],
+            Factor::Plain(Term::Not(factor)) if matches!(**factor, Factor::ZeroOrMore(..))
+        ));
+        assert!(matches!(
+            &exprs[1].0[0].0[..],
+            [
+                Factor::Plain(Term::Char('d')),
+                Factor::Plain(Term::Char('&'))
+            ]
+        ));
+
+        // Plain chars otherwise
+        let lexer = Lexer::new(r"(a&~b)".chars());
+        let mut parser = Parser::new(lexer);
+        let Ok(Group(groups)) = parser.parse_group() else {
+            panic!("expected a group")
+        };
+        assert_eq!(groups[0].1 .0[0].0.len(), 4);
+    }
+
     #[test]
     fn test_error_1() {
         let lexer = Lexer::new("((a|b)*)".chars());
@@ -0,0 +1,146 @@
+use super::{
+    ast::{Expr, Factor, FactorConn, Flags, FlagsModifier, Group, GroupKind, Term},
+    error::CompileError,
+};
+
+/// Mirror image of `group`, with `^`/`$` and lookaheads/lookbehinds swapped.
+/// Flags are first pinned on the terms they affect.
+pub(super) fn reverse(group: Group, flags: Flags) -> Result<Group, CompileError> {
+    let group = Pinning {
+        flags,
+        defaults: flags,
+    }
+    .group(group);
+    reverse_group(group)
+}
+
+/// Replaces flag groups by flags set on every term they affect.
+struct Pinning {
+    flags: Flags,
+    defaults: Flags,
+}
+
+impl Pinning {
+    fn group(&mut self, group: Group) -> Group {
+        let mut items = vec![];
+        for (kind, expr) in group.0 {
+            let flags = self.flags;
+            let kind = match kind {
+                GroupKind::Flags(modifier) => {
+                    self.flags = modifier.apply(flags);
+                    continue;
+                }
+                GroupKind::NonCapture(modifier) => {
+                    self.flags = modifier.apply(flags);
+                    GroupKind::NonCapture(FlagsModifier::default())
+                }
+                kind => kind,
+            };
+            items.push((kind, self.expr(expr)));
+            self.flags = flags;
+        }
+        Group(items)
+    }
+
+    fn expr(&mut self, expr: Expr) -> Expr {
+        Expr(
+            expr.0
+                .into_iter()
+                .map(|conn| FactorConn(conn.0.into_iter().map(|f| self.factor(f)).collect()))
+                .collect(),
+        )
+    }
+
+    fn factor(&mut self, factor: Factor) -> Factor {
+        match factor {
+            Factor::Plain(term) => Factor::Plain(self.term(term)),
+            Factor::ZeroOrOne(term, lazy) => Factor::ZeroOrOne(self.term(term), lazy),
+            Factor::ZeroOrMore(term, lazy) => Factor::ZeroOrMore(self.term(term), lazy),
+            Factor::OneOrMore(term, lazy) => Factor::OneOrMore(self.term(term), lazy),
+        }
+    }
+
+    fn term(&mut self, term: Term) -> Term {
+        match term {
+            Term::Group(group) => Term::Group(self.group(group)),
+            Term::Char(_) | Term::Class(..) | Term::Dot | Term::Caret | Term::Dollar
+                if self.flags != self.defaults =>
+            {
+                // Sets every flag, whatever those in scope
+                let flags = self.flags;
+                let modifier = FlagsModifier {
+                    on: flags,
+                    off: Flags {
+                        case_insensitive: !flags.case_insensitive,
+                        multi_line: !flags.multi_line,
+                        dot_all: !flags.dot_all,
+                        verbose: !flags.verbose,
+                    },
+                };
+                let expr = Expr(vec![FactorConn(vec![Factor::Plain(term)])]);
+                Term::Group(Group(vec![(GroupKind::NonCapture(modifier), expr)]))
+            }
+            term => term,
+        }
+    }
+}
+
+fn reverse_group(group: Group) -> Result<Group, CompileError> {
+    let items = group
+        .0
+        .into_iter()
+        .rev()
+        .map(|(kind, expr)| {
+            let kind = match kind {
+                GroupKind::LookAhead { negate } => GroupKind::LookBehind { negate },
+                GroupKind::LookBehind { negate } => GroupKind::LookAhead { negate },
+                kind => kind,
+            };
+            Ok((kind, reverse_expr(expr)?))
+        })
+        .collect::<Result<_, CompileError>>()?;
+    Ok(Group(items))
+}
+
+fn reverse_expr(expr: Expr) -> Result<Expr, CompileError> {
+    let conns = expr
+        .0
+        .into_iter()
+        .map(|conn| {
+            let factors = conn
+                .0
+                .into_iter()
+                .rev()
+                .map(reverse_factor)
+                .collect::<Result<_, _>>()?;
+            Ok(FactorConn(factors))
+        })
+        .collect::<Result<_, CompileError>>()?;
+    Ok(Expr(conns))
+}
+
+fn reverse_factor(factor: Factor) -> Result<Factor, CompileError> {
+    Ok(match factor {
+        Factor::Plain(term) => Factor::Plain(reverse_term(term)?),
+        Factor::ZeroOrOne(term, lazy) => Factor::ZeroOrOne(reverse_term(term)?, lazy),
+        Factor::ZeroOrMore(term, lazy) => Factor::ZeroOrMore(reverse_term(term)?, lazy),
+        Factor::OneOrMore(term, lazy) => Factor::OneOrMore(reverse_term(term)?, lazy),
+    })
+}
+
+fn reverse_term(term: Term) -> Result<Term, CompileError> {
+    Ok(match term {
+        Term::Caret => Term::Dollar,
+        Term::Dollar => Term::Caret,
+        Term::Group(group) => Term::Group(reverse_group(group)?),
+        Term::Backref(_) => return Err(CompileError::UnsupportedReverse("backreferences")),
+        Term::And(exprs) => Term::And(
+            exprs
+                .into_iter()
+                .map(reverse_expr)
+                .collect::<Result<_, _>>()?,
+        ),
+        Term::Not(factor) => Term::Not(Box::new(reverse_factor(*factor)?)),
+        term => term,
+    })
+}
@@ -1,26 +1,61 @@
 use std::fmt::Display;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Char(char),
+    /// Escaped char, never treated as syntax
+    Literal(char),
+    Backref(usize),
+    /// `\p{name}`, or `\P{name}` when negated
+    Property {
+        name: String,
+        negate: bool,
+    },
+    /// `\p{` running to the end of the pattern
+    UnclosedProperty(String),
+    Dot,
+    Caret,
+    Dollar,
     Alternate,
     ZeroOrOne,
     ZeroOrMore,
     OneOrMore,
     LeftBracket,
     RightBracket,
+    LeftSquare,
+    RightSquare,
+    /// `{` opening an interval in POSIX syntax
+    LeftCurly,
+    /// `[:name:]` inside a bracket expression
+    PosixClass(String),
+    /// `[.name.]` or `[=name=]` inside a bracket expression
+    Collating(String),
 }
 
 impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Char(c) => write!(f, "{}", c),
+            Self::Literal(c) => write!(f, "\\{}", c),
+            Self::Backref(n) => write!(f, "\\{}", n),
+            Self::Property { name, negate } => {
+                write!(f, "\\{}{{{}}}", if *negate { 'P' } else { 'p' }, name)
+            }
+            Self::UnclosedProperty(name) => write!(f, "\\p{{{}", name),
+            Self::Dot => write!(f, "."),
+            Self::Caret => write!(f, "^"),
+            Self::Dollar => write!(f, "$"),
             Self::Alternate => write!(f, "|"),
             Self::ZeroOrOne => write!(f, "?"),
             Self::ZeroOrMore => write!(f, "*"),
             Self::OneOrMore => write!(f, "+"),
             Self::LeftBracket => write!(f, "("),
             Self::RightBracket => write!(f, ")"),
+            Self::LeftSquare => write!(f, "["),
+            Self::RightSquare => write!(f, "]"),
+            Self::LeftCurly => write!(f, "{{"),
+            Self::PosixClass(name) => write!(f, "[:{}:]", name),
+            Self::Collating(name) => write!(f, "[.{}.]", name),
         }
     }
 }
@@ -1,15 +1,26 @@
 use std::{collections::HashMap, vec};
 
-use crate::vm::inst::{Inst, InstBlock, InstNode};
+use crate::{
+    class::CharClass,
+    vm::inst::{Anchor, Inst, InstBlock, InstNode, Lookaround},
+};
 
-use super::ast::{self, Factor};
+use super::ast::{self, Factor, Flags, GroupKind};
 
 #[derive(Default)]
 pub struct Transformer {
     nodes: Vec<InstNode>,
+    flags: Flags,
 }
 
 impl Transformer {
+    pub fn new(flags: Flags) -> Self {
+        Self {
+            flags,
+            ..Default::default()
+        }
+    }
+
     fn add_node(&mut self, node: InstNode) -> usize {
         let id = self.nodes.len();
         self.nodes.push(node);
@@ -20,10 +31,43 @@ impl Transformer {
 impl Transformer {
     fn transform_group(&mut self, ast: ast::Group) -> usize {
         let mut e_list = vec![];
-        for expr in ast.0 {
-            let id = self.transform_expr(expr);
+        for (kind, expr) in ast.0 {
+            let flags = self.flags;
+            let id = match kind {
+                GroupKind::Flags(modifier) => {
+                    self.flags = modifier.apply(flags);
+                    continue;
+                }
+                GroupKind::Capture { index, .. } => {
+                    let id = self.transform_expr(expr);
+                    self.add_node(InstNode(vec![
+                        InstBlock::Inst(Inst::Save(index * 2)),
+                        InstBlock::InstNodeIndex(id),
+                        InstBlock::Inst(Inst::Save(index * 2 + 1)),
+                    ]))
+                }
+                GroupKind::NonCapture(modifier) => {
+                    self.flags = modifier.apply(flags);
+                    self.transform_expr(expr)
+                }
+                GroupKind::LookAhead { negate } | GroupKind::LookBehind { negate } => {
+                    let max_len = expr.max_len();
+                    let id = self.transform_expr(expr);
+                    let look = Lookaround {
+                        behind: matches!(kind, GroupKind::LookBehind { .. }),
+                        negate,
+                        max_len,
+                        prog: self.generate(id),
+                    };
+                    self.add_node(InstNode::single_inst(Inst::Look(Box::new(look))))
+                }
+            };
+            self.flags = flags;
             e_list.push(InstBlock::InstNodeIndex(id));
         }
+        if e_list.is_empty() {
+            e_list.push(InstBlock::Inst(Inst::Noop));
+        }
         self.add_node(InstNode(e_list))
     }
 
@@ -58,25 +102,29 @@ impl Transformer {
             let id = self.transform_factor(factor);
             e_list.push(InstBlock::InstNodeIndex(id));
         }
+        if e_list.is_empty() {
+            e_list.push(InstBlock::Inst(Inst::Noop));
+        }
         self.add_node(InstNode(e_list))
     }
 
     fn transform_factor(&mut self, ast: ast::Factor) -> usize {
         match ast {
             Factor::Plain(term) => self.transform_term(term),
-            Factor::ZeroOrOne(term) => {
+            Factor::ZeroOrOne(term, lazy) => {
                 let l1 = self.transform_term(term);
                 let l2 = self.add_node(InstNode::single_inst(Inst::Noop));
                 self.add_node(InstNode(vec![
-                    InstBlock::Inst(Inst::Split(vec![l1, l2])),
+                    InstBlock::Inst(Inst::Split(prefer(l1, l2, lazy))),
                     InstBlock::InstNodeIndex(l1),
                     InstBlock::InstNodeIndex(l2),
                 ]))
             }
-            Factor::ZeroOrMore(term) => {
+            Factor::ZeroOrMore(term, lazy) => {
                 let e = self.transform_term(term);
                 let l3 = self.add_node(InstNode::single_inst(Inst::Noop));
-                let l1 = self.add_node(InstNode::single_inst(Inst::Split(vec![l3 + 2, l3])));
+                let l1 =
+                    self.add_node(InstNode::single_inst(Inst::Split(prefer(l3 + 2, l3, lazy))));
                 let l2 = self.add_node(InstNode(vec![
                     InstBlock::InstNodeIndex(e),
                     InstBlock::Inst(Inst::Jump(l1)),
@@ -87,12 +135,12 @@ impl Transformer {
                     InstBlock::InstNodeIndex(l3),
                 ]))
             }
-            Factor::OneOrMore(term) => {
+            Factor::OneOrMore(term, lazy) => {
                 let e = self.transform_term(term);
                 let l3 = self.add_node(InstNode::single_inst(Inst::Noop));
                 let l1 = self.add_node(InstNode(vec![
                     InstBlock::InstNodeIndex(e),
-                    InstBlock::Inst(Inst::Split(vec![l3 + 1, l3])),
+                    InstBlock::Inst(Inst::Split(prefer(l3 + 1, l3, lazy))),
                 ]));
                 self.add_node(InstNode(vec![
                     InstBlock::InstNodeIndex(l1),
@@ -104,13 +152,68 @@ impl Transformer {
 
     fn transform_term(&mut self, ast: ast::Term) -> usize {
         match ast {
+            ast::Term::Char(c) if self.flags.case_insensitive => {
+                let class = CharClass::from_char(c).case_fold();
+                let inst = match class.single() {
+                    Some(c) => Inst::Char(c),
+                    None => Inst::Class(class),
+                };
+                self.add_node(InstNode::single_inst(inst))
+            }
             ast::Term::Char(c) => self.add_node(InstNode::single_inst(Inst::Char(c))),
+            ast::Term::Class(class, negate) => {
+                let class = if self.flags.case_insensitive {
+                    class.case_fold()
+                } else {
+                    class
+                };
+                let class = if negate { class.negate() } else { class };
+                self.add_node(InstNode::single_inst(Inst::Class(class)))
+            }
+            ast::Term::Dot => {
+                let class = if self.flags.dot_all {
+                    CharClass::any()
+                } else {
+                    CharClass::from_char('\n').negate()
+                };
+                self.add_node(InstNode::single_inst(Inst::Class(class)))
+            }
+            ast::Term::Caret => {
+                let anchor = if self.flags.multi_line {
+                    Anchor::LineStart
+                } else {
+                    Anchor::Start
+                };
+                self.add_node(InstNode::single_inst(Inst::Assert(anchor)))
+            }
+            ast::Term::Dollar => {
+                let anchor = if self.flags.multi_line {
+                    Anchor::LineEnd
+                } else {
+                    Anchor::End
+                };
+                self.add_node(InstNode::single_inst(Inst::Assert(anchor)))
+            }
             ast::Term::Group(group) => self.transform_group(group),
+            ast::Term::Backref(n) => self.add_node(InstNode::single_inst(Inst::Backref(n))),
+            ast::Term::And(_) | ast::Term::Not(_) => {
+                unreachable!("boolean operators are only parsed for derivatives")
+            }
         }
     }
 
     pub fn transform(&mut self, ast: ast::Group) -> Vec<Inst> {
-        let start = self.transform_group(ast);
+        let group = self.transform_group(ast);
+        let start = self.add_node(InstNode(vec![
+            InstBlock::Inst(Inst::Save(0)),
+            InstBlock::InstNodeIndex(group),
+            InstBlock::Inst(Inst::Save(1)),
+        ]));
+        self.generate(start)
+    }
+
+    /// Lays out the instructions reachable from node `start` as a program.
+    fn generate(&self, start: usize) -> Vec<Inst> {
         let mut generator = InstructGenerator {
             inst_list: vec![],
             inst_mapping: HashMap::new(),
@@ -164,3 +267,12 @@ impl InstructGenerator {
         }
     }
 }
+
+/// Split targets in priority order, the greedy branch first unless `lazy`.
+fn prefer(greedy: usize, other: usize, lazy: bool) -> Vec<usize> {
+    if lazy {
+        vec![other, greedy]
+    } else {
+        vec![greedy, other]
+    }
+}
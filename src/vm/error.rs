@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchError {
+    #[error("Search gave up after {0} steps")]
+    StepLimitExceeded(u64),
+    #[error("Backreferences need the backtracking engine, run them with `backtracking_captures`")]
+    UnsupportedBackref,
+    #[error("Backward searches need a program compiled with `Builder::reverse`")]
+    NotReversed,
+    #[error("Byte offset {0} is not a char boundary of the input")]
+    InvalidPosition(usize),
+}
@@ -1,14 +1,52 @@
 use std::fmt::Display;
 
+use crate::class::CharClass;
+
 #[derive(Debug, Clone)]
 pub enum Inst {
     Char(char),
+    Class(CharClass),
+    Assert(Anchor),
     Split(Vec<usize>),
     Jump(usize),
+    Save(usize),
+    /// Only the backtracking engine runs it.
+    Backref(usize),
+    /// Zero-width assertion that runs a sub-program at the current position.
+    Look(Box<Lookaround>),
     Match,
     Noop,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Anchor {
+    Start,
+    End,
+    LineStart,
+    LineEnd,
+}
+
+impl Anchor {
+    /// Whether the anchor holds at byte offset `sp` of `input`.
+    pub fn holds(&self, input: &str, sp: usize) -> bool {
+        match self {
+            Anchor::Start => sp == 0,
+            Anchor::End => sp == input.len(),
+            Anchor::LineStart => sp == 0 || input[..sp].ends_with('\n'),
+            Anchor::LineEnd => sp == input.len() || input[sp..].starts_with('\n'),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Lookaround {
+    pub behind: bool,
+    pub negate: bool,
+    /// Longest text the sub-program can match in chars, `None` if unbounded.
+    pub max_len: Option<usize>,
+    pub prog: Vec<Inst>,
+}
+
 pub enum InstBlock {
     Inst(Inst),
     InstNodeIndex(usize),
@@ -26,6 +64,8 @@ impl Display for Inst {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Inst::Char(c) => write!(f, "char {}", c),
+            Inst::Class(class) => write!(f, "class {}", class),
+            Inst::Assert(anchor) => write!(f, "assert {:?}", anchor),
             Inst::Split(ids) => write!(
                 f,
                 "split {}",
@@ -35,6 +75,19 @@ impl Display for Inst {
                     .join(", ")
             ),
             Inst::Jump(id) => write!(f, "jmp {}", id),
+            Inst::Save(slot) => write!(f, "save {}", slot),
+            Inst::Backref(group) => write!(f, "backref {}", group),
+            Inst::Look(look) => write!(
+                f,
+                "look ?{}{} [{}]",
+                if look.behind { "<" } else { "" },
+                if look.negate { "!" } else { "=" },
+                look.prog
+                    .iter()
+                    .map(|inst| inst.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
             Inst::Match => write!(f, "match"),
             Inst::Noop => write!(f, "noop"),
         }
@@ -1,58 +1,923 @@
-use super::inst::Inst;
+use std::{cell::Cell, collections::HashSet};
+
+use memchr::memmem::{Finder, FinderRev};
+
+use super::{
+    error::MatchError,
+    inst::{Anchor, Inst, Lookaround},
+    program::Program,
+};
+
+/// Byte span of every capture group, `None` for groups that took no part.
+pub type Captures = Vec<Option<(usize, usize)>>;
+
+/// Upper bound on the size of the backtracker's visited set, in bits.
+const MAX_VISITED_BITS: usize = 256 * 1024 * 8;
 
 pub struct Interpreter {
-    prog: Vec<Inst>,
+    prog: Program,
+    backrefs: bool,
+    prefix: Finder<'static>,
+    inner: Finder<'static>,
+    /// Inner literal of a reversed program read back in the original order.
+    rinner: FinderRev<'static>,
 }
 
 impl Interpreter {
-    pub fn new(prog: Vec<Inst>) -> Self {
-        Self { prog }
+    pub fn new(prog: Program) -> Self {
+        let backrefs = has_backrefs(&prog);
+        let literals = prog.literals();
+        let prefix = Finder::new(literals.prefix()).into_owned();
+        let inner = Finder::new(literals.inner()).into_owned();
+        let rinner =
+            FinderRev::new(&literals.inner().chars().rev().collect::<String>()).into_owned();
+        Self {
+            prog,
+            backrefs,
+            prefix,
+            inner,
+            rinner,
+        }
     }
 
+    /// Whether the literals of the program rule out a match at offset 0.
+    fn rejects(&self, input: &str) -> bool {
+        !input.as_bytes().starts_with(self.prefix.needle())
+            || self.inner.find(input.as_bytes()).is_none()
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.prog
+    }
+
+    /// Longest input the backtracker runs on, longer ones go to the Pike VM.
+    pub fn max_backtrack_len(&self) -> usize {
+        (MAX_VISITED_BITS / self.prog.len().max(1)).saturating_sub(1)
+    }
+
+    /// Panics on backreferences, see [`Self::try_thompson_vm`].
     pub fn thompson_vm(&self, input: &str) -> bool {
-        let mut clist = vec![0 as usize];
-
-        for sp in input.chars().chain(std::iter::once('\0')) {
-            let mut nlist = vec![];
-            while let Some(pc) = clist.pop() {
-                match &self.prog[pc] {
-                    Inst::Char(c) => {
-                        if sp != *c {
-                            continue;
+        self.expect_no_backrefs();
+        self.thompson_vm_with(input, &Fuel::new(None))
+    }
+
+    /// Same as [`Self::thompson_vm`], failing on backreferences or out of steps.
+    pub fn try_thompson_vm(&self, input: &str) -> Result<bool, MatchError> {
+        self.check_no_backrefs()?;
+        let fuel = Fuel::new(self.prog.max_steps());
+        let matched = self.thompson_vm_with(input, &fuel);
+        fuel.check(matched)
+    }
+
+    fn thompson_vm_with(&self, input: &str, fuel: &Fuel) -> bool {
+        if let Some(aho_corasick) = self.prog.aho_corasick() {
+            return aho_corasick.is_match_at_start(input);
+        }
+        !self.rejects(input) && thompson(&self.prog, input, 0, None, fuel)
+    }
+
+    /// Searches from the occurrences of the literal prefix. Panics on backreferences.
+    pub fn thompson_search(&self, input: &str) -> bool {
+        self.expect_no_backrefs();
+        self.thompson_search_with(input, &Fuel::new(None))
+    }
+
+    /// Same as [`Self::thompson_search`], failing on backreferences or out of steps.
+    pub fn try_thompson_search(&self, input: &str) -> Result<bool, MatchError> {
+        self.check_no_backrefs()?;
+        let fuel = Fuel::new(self.prog.max_steps());
+        let matched = self.thompson_search_with(input, &fuel);
+        fuel.check(matched)
+    }
+
+    fn check_no_backrefs(&self) -> Result<(), MatchError> {
+        if self.backrefs {
+            return Err(MatchError::UnsupportedBackref);
+        }
+        Ok(())
+    }
+
+    fn expect_no_backrefs(&self) {
+        if let Err(err) = self.check_no_backrefs() {
+            panic!("{err}");
+        }
+    }
+
+    pub(super) fn thompson_search_with(&self, input: &str, fuel: &Fuel) -> bool {
+        if let Some(aho_corasick) = self.prog.aho_corasick() {
+            return aho_corasick.is_match(input);
+        }
+        self.inner.find(input.as_bytes()).is_some() && search(&self.prog, input, &self.prefix, fuel)
+    }
+
+    pub fn backtracking_vm(&self, input: &str) -> bool {
+        self.backtracking_captures(input).is_some()
+    }
+
+    /// Same as [`Self::backtracking_vm`], failing once out of steps.
+    pub fn try_backtracking_vm(&self, input: &str) -> Result<bool, MatchError> {
+        Ok(self.try_backtracking_captures(input)?.is_some())
+    }
+
+    /// Captures of the highest priority match, or of the longest one for
+    /// leftmost-longest programs. Programs with backreferences key the visited
+    /// set on the slots too, bounded by `Builder::max_steps` only.
+    pub fn backtracking_captures(&self, input: &str) -> Option<Captures> {
+        if self.rejects(input) {
+            return None;
+        }
+        self.backtracking_captures_at(input, 0, &Fuel::new(None))
+    }
+
+    /// Same as [`Self::backtracking_captures`], failing once out of steps.
+    pub fn try_backtracking_captures(&self, input: &str) -> Result<Option<Captures>, MatchError> {
+        if self.rejects(input) {
+            return Ok(None);
+        }
+        let fuel = Fuel::new(self.prog.max_steps());
+        let captures = self.backtracking_captures_at(input, 0, &fuel);
+        fuel.check(captures)
+    }
+
+    /// Anchored at `start`, without the prefilter.
+    pub(super) fn backtracking_captures_at(
+        &self,
+        input: &str,
+        start: usize,
+        fuel: &Fuel,
+    ) -> Option<Captures> {
+        self.captures_with(input, start, self.options(fuel))
+    }
+
+    /// Leftmost match from `start` on, in a single pass.
+    pub(super) fn leftmost_captures_at(
+        &self,
+        input: &str,
+        start: usize,
+        fuel: &Fuel,
+    ) -> Option<Captures> {
+        let options = Backtrack {
+            unanchored: true,
+            ..self.options(fuel)
+        };
+        self.captures_with(input, start, options)
+    }
+
+    fn captures_with(&self, input: &str, start: usize, options: Backtrack) -> Option<Captures> {
+        let slots = backtrack(&self.prog, options, input, self.slots(), start, None)?;
+        Some(slots.chunks(2).map(|pair| pair[0].zip(pair[1])).collect())
+    }
+
+    fn slots(&self) -> Vec<Option<usize>> {
+        vec![None; self.prog.captures_len() * 2]
+    }
+
+    fn options<'a>(&self, fuel: &'a Fuel) -> Backtrack<'a> {
+        Backtrack {
+            backrefs: self.backrefs,
+            longest: self.prog.leftmost_longest(),
+            unanchored: false,
+            fuel,
+        }
+    }
+
+    /// Span of the match ending the closest before `end`, for programs compiled
+    /// with `Builder::reverse`. `None` on invalid arguments, see [`Self::try_rfind`].
+    pub fn rfind(&self, input: &str, end: usize) -> Option<(usize, usize)> {
+        self.rfind_with(input, end, &Fuel::new(None)).ok()?
+    }
+
+    /// Same as [`Self::rfind`], failing on invalid arguments or out of steps.
+    pub fn try_rfind(&self, input: &str, end: usize) -> Result<Option<(usize, usize)>, MatchError> {
+        let fuel = Fuel::new(self.prog.max_steps());
+        let span = self.rfind_with(input, end, &fuel)?;
+        fuel.check(span)
+    }
+
+    fn rfind_with(
+        &self,
+        input: &str,
+        end: usize,
+        fuel: &Fuel,
+    ) -> Result<Option<(usize, usize)>, MatchError> {
+        if !self.prog.reversed() {
+            return Err(MatchError::NotReversed);
+        }
+        if !input.is_char_boundary(end) {
+            return Err(MatchError::InvalidPosition(end));
+        }
+        if self.rinner.rfind(&input.as_bytes()[..end]).is_none() {
+            return Ok(None);
+        }
+        let options = Backtrack {
+            unanchored: true,
+            ..self.options(fuel)
+        };
+        let Some(slots) = pike(
+            &self.prog,
+            options,
+            input,
+            self.slots(),
+            end,
+            None,
+            Direction::Backward,
+        ) else {
+            return Ok(None);
+        };
+        // The match was read from its end
+        Ok(slots[1].zip(slots[0]))
+    }
+}
+
+/// Step budget of a search, shared with its lookarounds.
+pub(super) struct Fuel {
+    limit: Option<u64>,
+    used: Cell<u64>,
+    spent: Cell<bool>,
+}
+
+impl Fuel {
+    pub(super) fn new(limit: Option<u64>) -> Self {
+        Self {
+            limit,
+            used: Cell::new(0),
+            spent: Cell::new(false),
+        }
+    }
+
+    /// Takes a step, or returns false if the budget is spent.
+    pub(super) fn burn(&self) -> bool {
+        if self.limit.is_some_and(|limit| self.used.get() >= limit) {
+            self.spent.set(true);
+            return false;
+        }
+        self.used.set(self.used.get() + 1);
+        true
+    }
+
+    pub(super) fn is_spent(&self) -> bool {
+        self.spent.get()
+    }
+
+    /// The result of the search, unless it ran out of steps.
+    pub(super) fn check<T>(&self, result: T) -> Result<T, MatchError> {
+        match self.limit {
+            Some(limit) if self.is_spent() => Err(MatchError::StepLimitExceeded(limit)),
+            _ => Ok(result),
+        }
+    }
+}
+
+/// With `end` set the match must stop exactly there.
+fn thompson(prog: &[Inst], input: &str, start: usize, end: Option<usize>, fuel: &Fuel) -> bool {
+    let haystack = &input[..end.unwrap_or(input.len())];
+    let mut clist = vec![0_usize];
+    let mut nlist = vec![];
+    let mut seen = Seen::new(prog.len());
+    let mut sp = start;
+
+    loop {
+        let c = haystack[sp..].chars().next();
+        if step(
+            prog, input, sp, end, &mut clist, &mut nlist, &mut seen, fuel,
+        ) {
+            return true;
+        }
+        match c {
+            Some(c) if !nlist.is_empty() => sp += c.len_utf8(),
+            _ => return false,
+        }
+        std::mem::swap(&mut clist, &mut nlist);
+    }
+}
+
+/// Unanchored [`thompson`] started at the occurrences of `prefix`.
+fn search(prog: &[Inst], input: &str, prefix: &Finder, fuel: &Fuel) -> bool {
+    let mut clist = vec![];
+    let mut nlist = vec![];
+    let mut seen = Seen::new(prog.len());
+    let mut sp = 0;
+
+    loop {
+        if fuel.is_spent() {
+            return false;
+        }
+        if clist.is_empty() {
+            match prefix.find(&input.as_bytes()[sp..]) {
+                Some(offset) => sp += offset,
+                None => return false,
+            }
+        }
+        if input.as_bytes()[sp..].starts_with(prefix.needle()) {
+            clist.push(0);
+        }
+        let c = input[sp..].chars().next();
+        if step(
+            prog, input, sp, None, &mut clist, &mut nlist, &mut seen, fuel,
+        ) {
+            return true;
+        }
+        match c {
+            Some(c) => sp += c.len_utf8(),
+            None => return false,
+        }
+        std::mem::swap(&mut clist, &mut nlist);
+    }
+}
+
+/// Moves the threads of `clist` reading the next char to `nlist`.
+#[allow(clippy::too_many_arguments)]
+fn step(
+    prog: &[Inst],
+    input: &str,
+    sp: usize,
+    end: Option<usize>,
+    clist: &mut Vec<usize>,
+    nlist: &mut Vec<usize>,
+    seen: &mut Seen,
+    fuel: &Fuel,
+) -> bool {
+    let c = input[sp..end.unwrap_or(input.len())].chars().next();
+    seen.clear();
+    nlist.clear();
+    while let Some(pc) = clist.pop() {
+        if !seen.insert(pc) {
+            continue;
+        }
+        if !fuel.burn() {
+            nlist.clear();
+            return false;
+        }
+        match &prog[pc] {
+            Inst::Char(c1) => {
+                if c != Some(*c1) {
+                    continue;
+                }
+                nlist.push(pc + 1);
+            }
+            Inst::Class(class) => {
+                if c.is_some_and(|c| class.contains(c)) {
+                    nlist.push(pc + 1);
+                }
+            }
+            Inst::Assert(anchor) => {
+                if anchor.holds(input, sp) {
+                    clist.push(pc + 1);
+                }
+            }
+            Inst::Jump(pc1) => {
+                clist.push(*pc1);
+            }
+            Inst::Split(pc_list) => {
+                for &pc in pc_list {
+                    clist.push(pc);
+                }
+            }
+            // Rejected before running
+            Inst::Backref(_) => {}
+            Inst::Look(look) => {
+                let run = |prog: &[Inst], input: &str, start, end| {
+                    thompson(prog, input, start, end, fuel)
+                };
+                if holds(look, input, sp, run) {
+                    clist.push(pc + 1);
+                }
+            }
+            Inst::Match => {
+                if end.is_none() || c.is_none() {
+                    return true;
+                }
+            }
+            Inst::Save(_) | Inst::Noop => {
+                clist.push(pc + 1);
+            }
+        }
+    }
+    false
+}
+
+/// Settings of a [`backtrack`] run.
+#[derive(Clone, Copy)]
+struct Backtrack<'a> {
+    backrefs: bool,
+    /// Explores every path to find the longest match.
+    longest: bool,
+    /// Tries every start from `start` on until one matches.
+    unanchored: bool,
+    fuel: &'a Fuel,
+}
+
+/// Returns the capture slots, on top of those set in `slots`.
+fn backtrack(
+    prog: &[Inst],
+    options: Backtrack,
+    input: &str,
+    mut slots: Vec<Option<usize>>,
+    start: usize,
+    end: Option<usize>,
+) -> Option<Vec<Option<usize>>> {
+    let haystack = &input[..end.unwrap_or(input.len())];
+    let mut visited = if options.backrefs {
+        Visited::States(HashSet::new())
+    } else if prog.len().saturating_mul(haystack.len() + 1 - start) > MAX_VISITED_BITS {
+        return pike(prog, options, input, slots, start, end, Direction::Forward);
+    } else {
+        Visited::bits(prog.len(), start, haystack.len() + 1)
+    };
+    let mut jobs = vec![Job::Step { pc: 0, sp: start }];
+    let mut best: Option<(usize, Vec<Option<usize>>)> = None;
+    let mut from = start;
+
+    loop {
+        while let Some(job) = jobs.pop() {
+            let (mut pc, mut sp) = match job {
+                Job::Step { pc, sp } => (pc, sp),
+                Job::Restore { slot, value } => {
+                    slots[slot] = value;
+                    continue;
+                }
+            };
+            while visited.insert(pc, sp, &slots) {
+                if !options.fuel.burn() {
+                    return None;
+                }
+                match &prog[pc] {
+                    Inst::Char(c) => match haystack[sp..].chars().next() {
+                        Some(c1) if c1 == *c => {
+                            pc += 1;
+                            sp += c1.len_utf8();
+                        }
+                        _ => break,
+                    },
+                    Inst::Class(class) => match haystack[sp..].chars().next() {
+                        Some(c1) if class.contains(c1) => {
+                            pc += 1;
+                            sp += c1.len_utf8();
+                        }
+                        _ => break,
+                    },
+                    Inst::Assert(anchor) => {
+                        if !anchor.holds(input, sp) {
+                            break;
                         }
-                        nlist.push(pc + 1);
+                        pc += 1;
                     }
-                    Inst::Jump(pc1) => {
-                        clist.push(*pc1);
+                    Inst::Jump(pc1) => pc = *pc1,
+                    Inst::Split(pc_list) => {
+                        // Push alternatives in reverse so the first one is tried first
+                        for &pc1 in pc_list.iter().skip(1).rev() {
+                            jobs.push(Job::Step { pc: pc1, sp });
+                        }
+                        pc = pc_list[0];
+                    }
+                    Inst::Save(slot) => {
+                        jobs.push(Job::Restore {
+                            slot: *slot,
+                            value: slots[*slot],
+                        });
+                        slots[*slot] = Some(sp);
+                        pc += 1;
+                    }
+                    Inst::Backref(group) => {
+                        let captured = slots
+                            .get(group * 2)
+                            .copied()
+                            .flatten()
+                            .zip(slots.get(group * 2 + 1).copied().flatten());
+                        match captured {
+                            Some((start, end))
+                                if haystack[sp..].starts_with(&input[start..end]) =>
+                            {
+                                sp += end - start;
+                                pc += 1;
+                            }
+                            _ => break,
+                        }
+                    }
+                    Inst::Look(look) => {
+                        // Backreferences in the sub-program see the outer groups
+                        let run = |prog: &[Inst], input: &str, start, end| {
+                            let options = Backtrack {
+                                backrefs: has_backrefs(prog),
+                                longest: false,
+                                unanchored: false,
+                                ..options
+                            };
+                            backtrack(prog, options, input, slots.clone(), start, end).is_some()
+                        };
+                        if !holds(look, input, sp, run) {
+                            break;
+                        }
+                        pc += 1;
+                    }
+                    Inst::Match => {
+                        if end.is_some() && sp != haystack.len() {
+                            break;
+                        }
+                        if !options.longest {
+                            return Some(slots);
+                        }
+                        if best.as_ref().is_none_or(|(sp1, _)| sp > *sp1) {
+                            best = Some((sp, slots.clone()));
+                        }
+                        break;
                     }
+                    Inst::Noop => pc += 1,
+                }
+            }
+        }
+        if best.is_some() || !options.unanchored {
+            break;
+        }
+        // Failed states stay failed from later starts
+        match haystack[from..].chars().next() {
+            Some(c) => from += c.len_utf8(),
+            None => break,
+        }
+        if !options.fuel.burn() {
+            return None;
+        }
+        jobs.push(Job::Step { pc: 0, sp: from });
+    }
+    best.map(|(_, slots)| slots)
+}
+
+/// Way a [`pike`] run reads its input.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Forward,
+    /// Right to left, for programs compiled with `Builder::reverse`.
+    Backward,
+}
+
+impl Direction {
+    /// Char at `sp` and the offset past it, without crossing `bound`.
+    fn read(self, input: &str, sp: usize, bound: usize) -> Option<(char, usize)> {
+        match self {
+            Direction::Forward => {
+                let c = input[sp..bound].chars().next()?;
+                Some((c, sp + c.len_utf8()))
+            }
+            Direction::Backward => {
+                let c = input[bound..sp].chars().next_back()?;
+                Some((c, sp - c.len_utf8()))
+            }
+        }
+    }
+
+    fn holds(self, anchor: &Anchor, input: &str, sp: usize) -> bool {
+        if self == Direction::Forward {
+            return anchor.holds(input, sp);
+        }
+        let mirror = match anchor {
+            Anchor::Start => Anchor::End,
+            Anchor::End => Anchor::Start,
+            Anchor::LineStart => Anchor::LineEnd,
+            Anchor::LineEnd => Anchor::LineStart,
+        };
+        mirror.holds(input, sp)
+    }
+}
+
+/// Thompson VM tracking capture slots. Backwards, `start` is the match end.
+fn pike(
+    prog: &[Inst],
+    options: Backtrack,
+    input: &str,
+    mut slots: Vec<Option<usize>>,
+    start: usize,
+    end: Option<usize>,
+    direction: Direction,
+) -> Option<Vec<Option<usize>>> {
+    let bound = match (end, direction) {
+        (Some(end), _) => end,
+        (None, Direction::Forward) => input.len(),
+        (None, Direction::Backward) => 0,
+    };
+    let mut clist = Threads::new(prog.len());
+    let mut nlist = Threads::new(prog.len());
+    // Start and end of the best match with its slots
+    let mut best: Option<(usize, usize, Vec<Option<usize>>)> = None;
+    clist.add(
+        prog,
+        input,
+        start,
+        start,
+        0,
+        &mut slots,
+        options.fuel,
+        direction,
+    );
+    let mut sp = start;
+
+    loop {
+        let read = direction.read(input, sp, bound);
+        let mut matched = None;
+        for (pc, from, mut slots) in clist.threads.drain(..) {
+            if matched.is_some_and(|matched| from != matched) {
+                // Started after the match, which is further left
+                break;
+            }
+            match (&prog[pc], read) {
+                (Inst::Char(c1), Some((c, _))) if c == *c1 => {}
+                (Inst::Class(class), Some((c, _))) if class.contains(c) => {}
+                (Inst::Match, _) => {
+                    if end.is_some() && sp != bound {
+                        continue;
+                    }
+                    matched = Some(from);
+                    if !options.longest {
+                        // Threads of lower priority lose to this match
+                        best = Some((from, sp, slots));
+                        break;
+                    }
+                    // Later starts are cut off, so this one is further left
+                    if best
+                        .as_ref()
+                        .is_none_or(|(from1, sp1, _)| from != *from1 || sp != *sp1)
+                    {
+                        best = Some((from, sp, slots));
+                    }
+                    continue;
+                }
+                _ => continue,
+            }
+            let next = read.unwrap().1;
+            nlist.add(
+                prog,
+                input,
+                next,
+                from,
+                pc + 1,
+                &mut slots,
+                options.fuel,
+                direction,
+            );
+        }
+        if options.fuel.is_spent() {
+            return None;
+        }
+        let Some((_, next)) = read else {
+            return best.map(|(_, _, slots)| slots);
+        };
+        if nlist.threads.is_empty() && (!options.unanchored || best.is_some()) {
+            return best.map(|(_, _, slots)| slots);
+        }
+        std::mem::swap(&mut clist, &mut nlist);
+        nlist.seen.clear();
+        sp = next;
+        if options.unanchored && best.is_none() {
+            if !options.fuel.burn() {
+                return None;
+            }
+            // Lowest priority, after the threads started further left
+            clist.add(prog, input, sp, sp, 0, &mut slots, options.fuel, direction);
+        }
+    }
+}
+
+/// Threads by priority with their start offset.
+struct Threads {
+    seen: Seen,
+    threads: Vec<(usize, usize, Vec<Option<usize>>)>,
+}
+
+impl Threads {
+    fn new(prog_len: usize) -> Self {
+        Self {
+            seen: Seen::new(prog_len),
+            threads: vec![],
+        }
+    }
+
+    /// Adds a thread for every char-reading or `Match` instruction reached.
+    #[allow(clippy::too_many_arguments)]
+    fn add(
+        &mut self,
+        prog: &[Inst],
+        input: &str,
+        sp: usize,
+        from: usize,
+        pc: usize,
+        slots: &mut [Option<usize>],
+        fuel: &Fuel,
+        direction: Direction,
+    ) {
+        let mut jobs = vec![Job::Step { pc, sp }];
+        while let Some(job) = jobs.pop() {
+            let mut pc = match job {
+                Job::Step { pc, .. } => pc,
+                Job::Restore { slot, value } => {
+                    slots[slot] = value;
+                    continue;
+                }
+            };
+            while self.seen.insert(pc) {
+                if !fuel.burn() {
+                    return;
+                }
+                match &prog[pc] {
+                    Inst::Char(_) | Inst::Class(_) | Inst::Match => {
+                        self.threads.push((pc, from, slots.to_vec()));
+                        break;
+                    }
+                    Inst::Assert(anchor) => {
+                        if !direction.holds(anchor, input, sp) {
+                            break;
+                        }
+                        pc += 1;
+                    }
+                    Inst::Jump(pc1) => pc = *pc1,
                     Inst::Split(pc_list) => {
-                        for &pc in pc_list {
-                            clist.push(pc);
+                        for &pc1 in pc_list.iter().skip(1).rev() {
+                            jobs.push(Job::Step { pc: pc1, sp });
                         }
+                        pc = pc_list[0];
+                    }
+                    Inst::Save(slot) => {
+                        jobs.push(Job::Restore {
+                            slot: *slot,
+                            value: slots[*slot],
+                        });
+                        slots[*slot] = Some(sp);
+                        pc += 1;
                     }
-                    Inst::Match => return true,
-                    Inst::Noop => {
-                        clist.push(pc + 1);
+                    Inst::Look(look) => {
+                        let held = if direction == Direction::Backward {
+                            holds_backward(look, input, sp, slots, fuel)
+                        } else {
+                            let run = |prog: &[Inst], input: &str, start, end| {
+                                thompson(prog, input, start, end, fuel)
+                            };
+                            holds(look, input, sp, run)
+                        };
+                        if !held {
+                            break;
+                        }
+                        pc += 1;
                     }
+                    Inst::Backref(_) => break,
+                    Inst::Noop => pc += 1,
                 }
             }
-            clist = nlist;
         }
-        false
+    }
+}
+
+/// Captures set inside the sub-program are not visible outside.
+fn holds<F>(look: &Lookaround, input: &str, sp: usize, run: F) -> bool
+where
+    F: Fn(&[Inst], &str, usize, Option<usize>) -> bool,
+{
+    let matched = if look.behind {
+        let starts = input[..sp]
+            .char_indices()
+            .rev()
+            .map(|(i, _)| i)
+            .take(look.max_len.unwrap_or(usize::MAX));
+        std::iter::once(sp)
+            .chain(starts)
+            .any(|start| run(&look.prog, input, start, Some(sp)))
+    } else {
+        run(&look.prog, input, sp, None)
+    };
+    matched != look.negate
+}
+
+/// [`holds`] for reversed programs, where lookbehinds were lookaheads.
+fn holds_backward(
+    look: &Lookaround,
+    input: &str,
+    sp: usize,
+    slots: &[Option<usize>],
+    fuel: &Fuel,
+) -> bool {
+    let run = |start, end| {
+        let options = Backtrack {
+            backrefs: false,
+            longest: false,
+            unanchored: false,
+            fuel,
+        };
+        let direction = Direction::Backward;
+        pike(
+            &look.prog,
+            options,
+            input,
+            slots.to_vec(),
+            start,
+            end,
+            direction,
+        )
+        .is_some()
+    };
+    let matched = if look.behind {
+        let starts = input[sp..]
+            .char_indices()
+            .map(|(i, c)| sp + i + c.len_utf8())
+            .take(look.max_len.unwrap_or(usize::MAX));
+        std::iter::once(sp)
+            .chain(starts)
+            .any(|start| run(start, Some(sp)))
+    } else {
+        run(sp, None)
+    };
+    matched != look.negate
+}
+
+/// Also looks into lookaround sub-programs.
+pub(super) fn has_backrefs(prog: &[Inst]) -> bool {
+    prog.iter().any(|inst| match inst {
+        Inst::Backref(_) => true,
+        Inst::Look(look) => has_backrefs(&look.prog),
+        _ => false,
+    })
+}
+
+/// Set cleared in constant time by bumping a generation counter.
+struct Seen {
+    marks: Vec<u32>,
+    generation: u32,
+}
+
+impl Seen {
+    fn new(prog_len: usize) -> Self {
+        Self {
+            marks: vec![0; prog_len],
+            generation: 1,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+        if self.generation == 0 {
+            self.marks.fill(0);
+            self.generation = 1;
+        }
+    }
+
+    /// Marks `pc`, returning `false` if it already was.
+    fn insert(&mut self, pc: usize) -> bool {
+        std::mem::replace(&mut self.marks[pc], self.generation) != self.generation
+    }
+}
+
+enum Job {
+    Step { pc: usize, sp: usize },
+    Restore { slot: usize, value: Option<usize> },
+}
+
+enum Visited {
+    Bits {
+        bits: Vec<u64>,
+        offset: usize,
+        stride: usize,
+    },
+    States(HashSet<(usize, usize, Vec<Option<usize>>)>),
+}
+
+impl Visited {
+    /// Visited set over the byte offsets `offset..stride`.
+    fn bits(prog_len: usize, offset: usize, stride: usize) -> Self {
+        let stride = stride - offset;
+        let len = prog_len * stride;
+        Self::Bits {
+            bits: vec![0; len.div_ceil(64)],
+            offset,
+            stride,
+        }
+    }
+
+    /// Marks the state as visited, returning `false` if it already was.
+    fn insert(&mut self, pc: usize, sp: usize, slots: &[Option<usize>]) -> bool {
+        match self {
+            Self::Bits {
+                bits,
+                offset,
+                stride,
+            } => {
+                let index = pc * *stride + sp - *offset;
+                let (word, bit) = (index / 64, 1 << (index % 64));
+                let fresh = bits[word] & bit == 0;
+                bits[word] |= bit;
+                fresh
+            }
+            Self::States(states) => states.insert((pc, sp, slots.to_vec())),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::vm::compile;
+    use crate::vm::{compile, compile_backtracking, Builder, CompileError, MatchError, Syntax};
 
     use super::*;
     #[test]
-    fn test_backtracking_vm() {
-        let interpreter = Interpreter::new(compile("(a+)"));
+    fn test_thompson_vm() {
+        let interpreter = Interpreter::new(compile("(a+)").unwrap());
         assert!(interpreter.thompson_vm("a"));
 
-        let interpreter = Interpreter::new(compile("(a+|b+)"));
+        let interpreter = Interpreter::new(compile("(a+|b+)").unwrap());
         assert!(interpreter.thompson_vm("a"));
         assert!(interpreter.thompson_vm("b"));
         assert!(interpreter.thompson_vm("aaa"));
@@ -61,9 +926,452 @@ mod tests {
         assert!(interpreter.thompson_vm("ba"));
         assert!(!interpreter.thompson_vm(""));
 
-        let interpreter = Interpreter::new(compile("(a+b*|c?d)"));
+        let interpreter = Interpreter::new(compile("(a+b*|c?d)").unwrap());
         assert!(interpreter.thompson_vm("aaab"));
         assert!(interpreter.thompson_vm("d"));
         assert!(!interpreter.thompson_vm("bc"));
     }
+
+    #[test]
+    fn test_prefilter() {
+        let interpreter = Interpreter::new(compile("(ERROR: [0-9]+)").unwrap());
+        assert_eq!(interpreter.program().literals().prefix(), "ERROR: ");
+        assert!(interpreter.thompson_vm("ERROR: 42"));
+        assert!(!interpreter.thompson_vm("INFO: 42"));
+        assert!(!interpreter.thompson_vm(" ERROR: 42"));
+        assert!(interpreter.backtracking_captures("INFO").is_none());
+
+        let interpreter = Interpreter::new(compile("([a-z]+=(?:on|off);)").unwrap());
+        assert_eq!(interpreter.program().literals().inner(), "=o");
+        assert!(interpreter.thompson_vm("x=off;"));
+        assert!(!interpreter.thompson_vm("x:on;"));
+    }
+
+    #[test]
+    fn test_thompson_search() {
+        let interpreter = Interpreter::new(compile("(ERROR: [0-9]+)").unwrap());
+        assert!(interpreter.thompson_search("12:00 ERROR: 42"));
+        assert!(interpreter.thompson_search("ERROR: x ERROR: 7"));
+        assert!(!interpreter.thompson_search("12:00 ERROR: none"));
+        assert!(!interpreter.thompson_search("12:00 INFO: 42"));
+
+        let interpreter = Interpreter::new(compile("([0-9]+ms|$)").unwrap());
+        assert!(interpreter.thompson_search("took 12ms"));
+        assert!(interpreter.thompson_search("no time"));
+
+        let interpreter = Interpreter::new(compile("(^a|(?<=x)b)").unwrap());
+        assert!(interpreter.thompson_search("xb"));
+        assert!(!interpreter.thompson_search("ba"));
+        assert!(interpreter.thompson_search("ab"));
+    }
+
+    #[test]
+    fn test_rfind() {
+        let rfind = |expr: &str, input: &str, end: usize| {
+            let prog = Builder::new().reverse(true).build(expr).unwrap();
+            Interpreter::new(prog).rfind(input, end)
+        };
+        assert_eq!(rfind("(ab+c)", "abc abbc abbbc", 14), Some((9, 14)));
+        assert_eq!(rfind("(ab+c)", "abc abbc abbbc", 13), Some((4, 8)));
+        assert_eq!(rfind("(ab+c)", "abc abbc abbbc", 2), None);
+        assert_eq!(rfind("(λ+ο)", "λλο λο", 7), Some((0, 6)));
+        assert_eq!(rfind("([0-9]*)", "ab12", 4), Some((2, 4)));
+
+        // Anchors keep their meaning in the original input
+        assert_eq!(rfind("(^a)", "aaa", 3), Some((0, 1)));
+        assert_eq!(rfind("(a$)", "aaa", 2), None);
+        assert_eq!(rfind("((?m)^a)", "a\na\nb", 5), Some((2, 3)));
+
+        // Lookarounds still look the same way
+        assert_eq!(rfind("(a(?=b))", "abaab", 5), Some((3, 4)));
+        assert_eq!(rfind("((?<!x)a)", "aaxa", 4), Some((1, 2)));
+
+        // Flags apply to what follows them only
+        assert_eq!(rfind("(a(?i)b)", "aB AB", 5), Some((0, 2)));
+        assert_eq!(rfind("((?i:a)b)", "Ab aB", 5), Some((0, 2)));
+
+        // POSIX leftmost is the rightmost start going backwards
+        let prog = Builder::new()
+            .reverse(true)
+            .syntax(Syntax::PosixExtended)
+            .build("a|ab|b")
+            .unwrap();
+        assert_eq!(Interpreter::new(prog).rfind("xab", 3), Some((1, 3)));
+
+        // A thread starts at every position, in a single pass
+        let input = format!("{}abc", "ab ".repeat(100_000));
+        let len = input.len();
+        assert_eq!(rfind("((a|ab)c)", &input, len), Some((len - 3, len)));
+        assert_eq!(rfind("((a|ab)c)", &input, len - 1), None);
+        assert_eq!(rfind("(b )", &input, len - 1), Some((len - 5, len - 3)));
+
+        // Bad arguments
+        assert_eq!(rfind("(ab+c)", "abc", 4), None);
+        assert_eq!(rfind("(λ)", "λ", 1), None);
+        let prog = Builder::new().reverse(true).build("(abc)").unwrap();
+        let interpreter = Interpreter::new(prog);
+        assert_eq!(
+            interpreter.try_rfind("abc", 4),
+            Err(MatchError::InvalidPosition(4))
+        );
+        assert_eq!(interpreter.try_rfind("abc", 3), Ok(Some((0, 3))));
+        let interpreter = Interpreter::new(compile("(abc)").unwrap());
+        assert_eq!(interpreter.rfind("abc", 3), None);
+        assert_eq!(
+            interpreter.try_rfind("abc", 3),
+            Err(MatchError::NotReversed)
+        );
+
+        let prog = Builder::new().reverse(true).build("(abc|de)").unwrap();
+        assert!(Interpreter::new(prog).thompson_vm("edx"));
+        assert!(matches!(
+            Builder::new()
+                .reverse(true)
+                .backtracking(true)
+                .build(r"((a)\2)"),
+            Err(CompileError::UnsupportedReverse(_))
+        ));
+    }
+
+    #[test]
+    fn test_step_limit() {
+        let input = "a".repeat(1000);
+        let prog = Builder::new()
+            .max_steps(100)
+            .build("((a|b)*(c|d))")
+            .unwrap();
+        let interpreter = Interpreter::new(prog);
+        assert_eq!(interpreter.try_thompson_search("abc"), Ok(true));
+        assert_eq!(
+            interpreter.try_thompson_search(&input),
+            Err(MatchError::StepLimitExceeded(100))
+        );
+        assert!(!interpreter.thompson_search(&input));
+
+        let prog = Builder::new()
+            .max_steps(100_000)
+            .build("((a|b)*(c|d))")
+            .unwrap();
+        assert_eq!(
+            Interpreter::new(prog).try_thompson_search(&input),
+            Ok(false)
+        );
+
+        // Keyed on the capture slots, the visited set barely prunes
+        let prog = Builder::new()
+            .backtracking(true)
+            .max_steps(1_000)
+            .build(r"((a*)*\2(b|c))")
+            .unwrap();
+        let interpreter = Interpreter::new(prog);
+        let matching = format!("{}b", "a".repeat(30));
+        assert_eq!(
+            interpreter.try_backtracking_captures(&matching),
+            Ok(interpreter.backtracking_captures(&matching))
+        );
+        assert_eq!(
+            interpreter.try_backtracking_captures(&format!("{}d", "a".repeat(30))),
+            Err(MatchError::StepLimitExceeded(1_000))
+        );
+        assert_eq!(
+            interpreter.try_backtracking_vm(&format!("{}d", "a".repeat(30))),
+            Err(MatchError::StepLimitExceeded(1_000))
+        );
+        assert_eq!(interpreter.try_backtracking_vm(&matching), Ok(true));
+    }
+
+    #[test]
+    fn test_aho_corasick() {
+        let interpreter = Interpreter::new(compile("(foo|bar|baz)").unwrap());
+        assert!(interpreter.program().aho_corasick().is_some());
+        assert!(interpreter.thompson_vm("barn"));
+        assert!(!interpreter.thompson_vm("abaz"));
+        assert!(interpreter.thompson_search("abaz"));
+        assert!(!interpreter.thompson_search("ba fo"));
+        assert!(interpreter.backtracking_vm("bazaar"));
+
+        let interpreter = Interpreter::new(compile("(foo|ba+)").unwrap());
+        assert!(interpreter.program().aho_corasick().is_none());
+    }
+
+    #[test]
+    fn test_backtracking_vm() {
+        let interpreter = Interpreter::new(compile("(a+)").unwrap());
+        assert!(interpreter.backtracking_vm("a"));
+
+        let interpreter = Interpreter::new(compile("(a+|b+)").unwrap());
+        assert!(interpreter.backtracking_vm("a"));
+        assert!(interpreter.backtracking_vm("b"));
+        assert!(interpreter.backtracking_vm("aaa"));
+        assert!(interpreter.backtracking_vm("bbb"));
+        assert!(interpreter.backtracking_vm("ab"));
+        assert!(interpreter.backtracking_vm("ba"));
+        assert!(!interpreter.backtracking_vm(""));
+
+        let interpreter = Interpreter::new(compile("(a+b*|c?d)").unwrap());
+        assert!(interpreter.backtracking_vm("aaab"));
+        assert!(interpreter.backtracking_vm("d"));
+        assert!(!interpreter.backtracking_vm("bc"));
+    }
+
+    #[test]
+    fn test_backtracking_captures() {
+        let interpreter = Interpreter::new(compile("(a(b|c)*d)").unwrap());
+        assert_eq!(interpreter.program().captures_len(), 3);
+        assert_eq!(
+            interpreter.backtracking_captures("abcbde"),
+            Some(vec![Some((0, 5)), Some((0, 5)), Some((3, 4))])
+        );
+        assert_eq!(
+            interpreter.backtracking_captures("ad"),
+            Some(vec![Some((0, 2)), Some((0, 2)), None])
+        );
+        assert_eq!(interpreter.backtracking_captures("abc"), None);
+
+        // Pathological for naive backtracking, linear with the visited set
+        let interpreter = Interpreter::new(compile("((a*)*b)").unwrap());
+        assert!(!interpreter.backtracking_vm(&"a".repeat(1000)));
+
+        // Too long for the visited set, run by the Pike VM
+        let interpreter = Interpreter::new(compile("(a(b|c)*d)").unwrap());
+        let n = interpreter.max_backtrack_len();
+        let input = format!("a{}d", "b".repeat(n));
+        assert_eq!(
+            interpreter.backtracking_captures(&input),
+            Some(vec![Some((0, n + 2)), Some((0, n + 2)), Some((n, n + 1))])
+        );
+        assert_eq!(interpreter.backtracking_captures(&input[..n + 1]), None);
+        let prog = Builder::new()
+            .syntax(Syntax::PosixExtended)
+            .build("(a|ab)(c|bcd)")
+            .unwrap();
+        let interpreter = Interpreter::new(prog);
+        let input = format!("abcd{}", "x".repeat(interpreter.max_backtrack_len()));
+        assert_eq!(
+            interpreter.backtracking_captures(&input),
+            Some(vec![Some((0, 4)), Some((0, 1)), Some((1, 4))])
+        );
+    }
+
+    #[test]
+    fn test_named_captures() {
+        let interpreter = Interpreter::new(compile("((?:a|b)+=(?P<value>c+))").unwrap());
+        let captures = interpreter.backtracking_captures("ab=cc").unwrap();
+        let value = interpreter.program().capture_index("value").unwrap();
+        assert_eq!(captures.len(), 3);
+        assert_eq!(captures[value], Some((3, 5)));
+    }
+
+    #[test]
+    fn test_lookaround() {
+        // At least one digit, not starting with a letter o
+        let interpreter = Interpreter::new(compile("((?=(a|b)*1)(?!o)(a|b|o|1)+)").unwrap());
+        for engine in [Interpreter::thompson_vm, Interpreter::backtracking_vm] {
+            assert!(engine(&interpreter, "ab1"));
+            assert!(engine(&interpreter, "1o"));
+            assert!(!engine(&interpreter, "ab"));
+            assert!(!engine(&interpreter, "o1"));
+        }
+
+        let interpreter = Interpreter::new(compile("(a(?<=ba)b|(?<!a)cd)").unwrap());
+        for engine in [Interpreter::thompson_vm, Interpreter::backtracking_vm] {
+            assert!(engine(&interpreter, "cd"));
+            assert!(!engine(&interpreter, "ab"));
+        }
+
+        let interpreter = Interpreter::new(compile_backtracking("(a+(?<=ba+)c)").unwrap());
+        assert!(!interpreter.backtracking_vm("aac"));
+        let interpreter = Interpreter::new(compile_backtracking("(ba+(?<=ba+)c)").unwrap());
+        assert!(interpreter.backtracking_vm("baac"));
+
+        // Backreferences inside lookarounds see the groups captured before
+        let interpreter = Interpreter::new(compile_backtracking(r"((a|b)(?=\2))").unwrap());
+        assert_eq!(
+            interpreter.backtracking_captures("aa"),
+            Some(vec![Some((0, 1)), Some((0, 1)), Some((0, 1))])
+        );
+        assert!(!interpreter.backtracking_vm("ab"));
+        let interpreter = Interpreter::new(compile_backtracking(r"((a|b)x(?<=\2x))").unwrap());
+        assert!(interpreter.backtracking_vm("bx"));
+    }
+
+    #[test]
+    fn test_flags() {
+        let interpreter = Interpreter::new(compile("(x(?i:y)z|(?i)ab(?-i:c))").unwrap());
+        for engine in [Interpreter::thompson_vm, Interpreter::backtracking_vm] {
+            assert!(engine(&interpreter, "ABc"));
+            assert!(!engine(&interpreter, "ABC"));
+            assert!(engine(&interpreter, "xYz"));
+            assert!(!engine(&interpreter, "XYz"));
+        }
+
+        let interpreter = Interpreter::new(compile("(a.b$)").unwrap());
+        assert!(interpreter.thompson_vm("a-b"));
+        assert!(!interpreter.thompson_vm("a\nb"));
+        assert!(!interpreter.thompson_vm("a-bc"));
+
+        let interpreter = Interpreter::new(compile("((?sm)a.b$)").unwrap());
+        assert!(interpreter.thompson_vm("a\nb"));
+        assert!(interpreter.thompson_vm("a-b\nc"));
+
+        let interpreter = Interpreter::new(compile("((?m)a\n^b)").unwrap());
+        assert!(interpreter.backtracking_vm("a\nb"));
+
+        let interpreter = Interpreter::new(
+            Builder::new()
+                .case_insensitive(true)
+                .verbose(true)
+                .build("( a + \\  b  # trailing comment\n )")
+                .unwrap(),
+        );
+        for engine in [Interpreter::thompson_vm, Interpreter::backtracking_vm] {
+            assert!(engine(&interpreter, "aA B"));
+            assert!(!engine(&interpreter, "aAB"));
+        }
+
+        // Comments are raw text up to the end of the line, in scope only
+        for (expr, matching, other) in [
+            ("((?x)a # see [x\nb)", "ab", "a b"),
+            ("((?x)a # end\\\nb)", "ab", "a\\b"),
+            ("((?x: a # (\n)b c)", "ab c", "abc"),
+        ] {
+            let interpreter = Interpreter::new(compile(expr).unwrap());
+            assert!(interpreter.thompson_vm(matching), "{expr}");
+            assert!(!interpreter.thompson_vm(other), "{expr}");
+        }
+    }
+
+    #[test]
+    fn test_unicode_case_folding() {
+        let interpreter = Interpreter::new(compile("((?i)ok σ)").unwrap());
+        for engine in [Interpreter::thompson_vm, Interpreter::backtracking_vm] {
+            assert!(engine(&interpreter, "OK Σ"));
+            assert!(engine(&interpreter, "o\u{212A} ς"));
+            assert!(!engine(&interpreter, "ok s"));
+        }
+    }
+
+    #[test]
+    fn test_unicode_property() {
+        let interpreter = Interpreter::new(compile(r"(\p{Greek}+ \P{L}$)").unwrap());
+        for engine in [Interpreter::thompson_vm, Interpreter::backtracking_vm] {
+            assert!(engine(&interpreter, "λόγος 1"));
+            assert!(!engine(&interpreter, "logos 1"));
+            assert!(!engine(&interpreter, "λόγος a"));
+        }
+    }
+
+    #[test]
+    fn test_backtracking_backref() {
+        // Repeated word detection
+        let interpreter = Interpreter::new(compile_backtracking(r"(((a|b)+) \2)").unwrap());
+        assert!(interpreter.backtracking_vm("ab ab"));
+        assert!(interpreter.backtracking_vm("bab bab"));
+        assert!(!interpreter.backtracking_vm("ab ba"));
+        assert!(!interpreter.backtracking_vm("aba ab"));
+        assert_eq!(
+            interpreter.backtracking_captures("aba aba"),
+            Some(vec![Some((0, 7)), Some((0, 7)), Some((0, 3)), Some((2, 3))])
+        );
+
+        // The Thompson VM refuses backreferences rather than not matching
+        for expr in [r"((a)\2)", r"((a)(?=\2))"] {
+            let interpreter = Interpreter::new(compile_backtracking(expr).unwrap());
+            assert_eq!(
+                interpreter.try_thompson_vm("aa"),
+                Err(MatchError::UnsupportedBackref)
+            );
+            assert_eq!(
+                interpreter.try_thompson_search("aa"),
+                Err(MatchError::UnsupportedBackref)
+            );
+        }
+        let interpreter = Interpreter::new(compile("(a+)").unwrap());
+        assert_eq!(interpreter.try_thompson_vm("aa"), Ok(true));
+    }
+
+    #[test]
+    #[should_panic(expected = "Backreferences need the backtracking engine")]
+    fn test_thompson_vm_backref() {
+        let interpreter = Interpreter::new(compile_backtracking(r"((a)\2)").unwrap());
+        interpreter.thompson_vm("aa");
+    }
+
+    #[test]
+    fn test_bracket() {
+        let interpreter = Interpreter::new(compile(r"([]a-c[:digit:]-]+[^x\n])").unwrap());
+        assert!(interpreter.thompson_vm("]-b9z"));
+        assert!(interpreter.backtracking_vm("a\t"));
+        assert!(!interpreter.thompson_vm("ax"));
+        assert!(!interpreter.thompson_vm("d1"));
+
+        let interpreter = Interpreter::new(
+            Builder::new()
+                .case_insensitive(true)
+                .build("([^[:lower:]])")
+                .unwrap(),
+        );
+        assert!(interpreter.thompson_vm("1"));
+        assert!(!interpreter.thompson_vm("A"));
+    }
+
+    #[test]
+    fn test_lazy() {
+        let interpreter = Interpreter::new(compile("((a+?)(a*))").unwrap());
+        assert_eq!(
+            interpreter.backtracking_captures("aaa"),
+            Some(vec![Some((0, 3)), Some((0, 3)), Some((0, 1)), Some((1, 3))])
+        );
+        let interpreter = Interpreter::new(compile("((a??)(a*?)b)").unwrap());
+        assert_eq!(
+            interpreter.backtracking_captures("aab"),
+            Some(vec![Some((0, 3)), Some((0, 3)), Some((0, 0)), Some((0, 2))])
+        );
+    }
+
+    #[test]
+    fn test_posix_syntax() {
+        let prog = Builder::new()
+            .syntax(Syntax::PosixExtended)
+            .build("(a|ab)(c|bcd)")
+            .unwrap();
+        let interpreter = Interpreter::new(prog);
+        assert!(interpreter.thompson_vm("abcd"));
+        assert_eq!(
+            interpreter.backtracking_captures("abcd"),
+            Some(vec![Some((0, 4)), Some((0, 1)), Some((1, 4))])
+        );
+
+        let prog = Builder::new()
+            .syntax(Syntax::PosixExtended)
+            .build(r"[[:alpha:]_][[:alnum:]_\]*")
+            .unwrap();
+        let interpreter = Interpreter::new(prog);
+        assert_eq!(
+            interpreter.backtracking_captures(r"x_1\y+"),
+            Some(vec![Some((0, 5))])
+        );
+        assert!(Builder::new()
+            .syntax(Syntax::PosixExtended)
+            .build("(?:a)")
+            .is_err());
+        // Intervals as in `grep -E`
+        let ere = |expr: &str| {
+            let prog = Builder::new()
+                .syntax(Syntax::PosixExtended)
+                .build(expr)
+                .unwrap();
+            Interpreter::new(prog)
+        };
+        let interpreter = ere("a{2}");
+        assert!(interpreter.thompson_vm("aa"));
+        assert!(!interpreter.thompson_vm("a{2}"));
+        assert_eq!(
+            ere("^(a|b){2,3}$").backtracking_captures("aba"),
+            Some(vec![Some((0, 3)), Some((2, 3))])
+        );
+        assert!(!ere("^(a|b){2,3}$").thompson_vm("abab"));
+        assert!(ere("^x{2,}y{,1}$").thompson_vm("xxxy"));
+        assert!(!ere("^x{2,}y{,1}$").thompson_vm("xyy"));
+    }
 }
@@ -1,6 +1,19 @@
+mod aho_corasick;
 mod compiler;
+mod error;
 mod inst;
 mod interpreter;
+mod onepass;
+mod program;
+mod regex;
 
-pub use compiler::compile;
-pub use interpreter::Interpreter;
+pub use aho_corasick::{AhoCorasick, Match, Matches, OverlappingMatches};
+pub use compiler::{
+    compile, compile_backtracking, print_prog, Builder, CompileError, Derivatives, Literals,
+    ParseError, Syntax,
+};
+pub use error::MatchError;
+pub use interpreter::{Captures, Interpreter};
+pub use onepass::OnePass;
+pub use program::Program;
+pub use regex::{Regex, Strategy};
@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+
+use crate::class::CharClass;
+
+use super::{
+    error::MatchError,
+    inst::{Anchor, Inst},
+    interpreter::{Captures, Fuel},
+    program::Program,
+};
+
+/// Matcher for programs where the next char alone decides the way to go,
+/// setting the capture slots in a single scan.
+pub struct OnePass {
+    states: Vec<State>,
+    slots: usize,
+    max_steps: Option<u64>,
+}
+
+const NONE: u32 = u32::MAX;
+
+/// Program position where a char was just read, or the start.
+struct State {
+    transitions: Vec<Transition>,
+    /// Index of the transition reading each ASCII char, [`NONE`] if none.
+    ascii: Vec<u32>,
+    /// What reaching `Match` from here needs and saves.
+    matching: Option<Actions>,
+}
+
+struct Transition {
+    class: CharClass,
+    actions: Actions,
+    target: u32,
+    /// Whether a match at the state takes priority over the transition.
+    after_match: bool,
+}
+
+/// Anchors checked and slots saved along the epsilon path to an instruction.
+#[derive(Debug, Clone, Default)]
+struct Actions {
+    asserts: Vec<Anchor>,
+    saves: Vec<usize>,
+}
+
+impl Actions {
+    fn holds(&self, input: &str, sp: usize) -> bool {
+        self.asserts.iter().all(|anchor| anchor.holds(input, sp))
+    }
+
+    fn save(&self, slots: &mut [Option<usize>], sp: usize) {
+        for &slot in &self.saves {
+            slots[slot] = Some(sp);
+        }
+    }
+}
+
+impl OnePass {
+    /// `None` if `prog` is not one-pass.
+    pub fn new(prog: &Program) -> Option<Self> {
+        let mut ids = HashMap::from([(0, 0)]);
+        let mut pcs = vec![0];
+        let mut states = vec![];
+        while let Some(&pc) = pcs.get(states.len()) {
+            let (reads, matching) = closure(prog, pc)?;
+            let mut transitions = vec![];
+            for (class, actions, pc, after_match) in reads {
+                let target = *ids.entry(pc).or_insert_with(|| {
+                    pcs.push(pc);
+                    pcs.len() as u32 - 1
+                });
+                transitions.push(Transition {
+                    class,
+                    actions,
+                    target,
+                    after_match: after_match && !prog.leftmost_longest(),
+                });
+            }
+            let mut ascii = vec![NONE; 128];
+            for (i, transition) in transitions.iter().enumerate() {
+                for (c, index) in ascii.iter_mut().enumerate() {
+                    if transition.class.contains(c as u8 as char) {
+                        *index = i as u32;
+                    }
+                }
+            }
+            states.push(State {
+                transitions,
+                ascii,
+                matching,
+            });
+        }
+        Some(Self {
+            states,
+            slots: prog.captures_len() * 2,
+            max_steps: prog.max_steps(),
+        })
+    }
+
+    pub fn is_match(&self, input: &str) -> bool {
+        self.captures(input).is_some()
+    }
+
+    /// Same captures as `Interpreter::backtracking_captures`.
+    pub fn captures(&self, input: &str) -> Option<Captures> {
+        self.captures_at(input, 0, &Fuel::new(None))
+    }
+
+    /// Same as [`Self::is_match`], failing once out of steps.
+    pub fn try_is_match(&self, input: &str) -> Result<bool, MatchError> {
+        Ok(self.try_captures(input)?.is_some())
+    }
+
+    /// Same as [`Self::captures`], taking a step per char.
+    pub fn try_captures(&self, input: &str) -> Result<Option<Captures>, MatchError> {
+        let fuel = Fuel::new(self.max_steps);
+        let captures = self.captures_at(input, 0, &fuel);
+        fuel.check(captures)
+    }
+
+    /// Same as [`Self::captures`] for the match starting at byte offset `start`.
+    pub(super) fn captures_at(&self, input: &str, start: usize, fuel: &Fuel) -> Option<Captures> {
+        let mut slots = vec![None; self.slots];
+        let mut best = None;
+        let mut state = &self.states[0];
+        let mut sp = start;
+        loop {
+            if !fuel.burn() {
+                return None;
+            }
+            let matched = match &state.matching {
+                Some(actions) if actions.holds(input, sp) => {
+                    let mut slots = slots.clone();
+                    actions.save(&mut slots, sp);
+                    best = Some(slots);
+                    true
+                }
+                _ => false,
+            };
+            let Some(c) = input[sp..].chars().next() else {
+                break;
+            };
+            let transition = match state.ascii.get(c as usize) {
+                Some(&NONE) => None,
+                Some(&i) => Some(&state.transitions[i as usize]),
+                None => state.transitions.iter().find(|t| t.class.contains(c)),
+            };
+            let Some(transition) = transition else {
+                break;
+            };
+            if (matched && transition.after_match) || !transition.actions.holds(input, sp) {
+                break;
+            }
+            transition.actions.save(&mut slots, sp);
+            sp += c.len_utf8();
+            state = &self.states[transition.target as usize];
+        }
+        best.map(|slots| slots.chunks(2).map(|pair| pair[0].zip(pair[1])).collect())
+    }
+}
+
+type Read = (CharClass, Actions, usize, bool);
+
+/// Instructions reading a char reached from `start` by priority, `None` if
+/// the program is not one-pass there.
+fn closure(prog: &[Inst], start: usize) -> Option<(Vec<Read>, Option<Actions>)> {
+    let mut seen = vec![false; prog.len()];
+    let mut stack = vec![(start, Actions::default())];
+    let mut reads = vec![];
+    let mut matching = None;
+    while let Some((pc, mut actions)) = stack.pop() {
+        if std::mem::replace(&mut seen[pc], true) {
+            return None;
+        }
+        match &prog[pc] {
+            Inst::Char(c) => {
+                reads.push((
+                    CharClass::from_char(*c),
+                    actions,
+                    pc + 1,
+                    matching.is_some(),
+                ));
+            }
+            Inst::Class(class) => {
+                reads.push((class.clone(), actions, pc + 1, matching.is_some()));
+            }
+            Inst::Assert(anchor) => {
+                actions.asserts.push(*anchor);
+                stack.push((pc + 1, actions));
+            }
+            Inst::Jump(pc1) => stack.push((*pc1, actions)),
+            Inst::Split(pc_list) => {
+                for &pc1 in pc_list.iter().rev() {
+                    stack.push((pc1, actions.clone()));
+                }
+            }
+            Inst::Save(slot) => {
+                actions.saves.push(*slot);
+                stack.push((pc + 1, actions));
+            }
+            Inst::Noop => stack.push((pc + 1, actions)),
+            Inst::Match => {
+                if matching.replace(actions).is_some() {
+                    return None;
+                }
+            }
+            Inst::Backref(_) | Inst::Look(_) => return None,
+        }
+    }
+    // The ranges of a class are disjoint, so overlaps are between classes
+    let mut ranges: Vec<_> = reads
+        .iter()
+        .flat_map(|(class, ..)| class.ranges().iter().copied())
+        .collect();
+    ranges.sort_unstable();
+    if ranges.windows(2).any(|pair| pair[1].0 <= pair[0].1) {
+        return None;
+    }
+    Some((reads, matching))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vm::{compile, Builder, Interpreter, Syntax};
+
+    use super::*;
+
+    #[test]
+    fn test_one_pass() {
+        for expr in ["(key=([a-z]+);)", "((a|b)*c)", "(x(?:y|z)?$)", "(a+?)"] {
+            assert!(OnePass::new(&compile(expr).unwrap()).is_some(), "{expr}");
+        }
+        for expr in [
+            "(a*a)",
+            "((a|ab)c)",
+            "(a(?=b))",
+            "((a*)*)",
+            "(a*|b*)",
+            "(x=(?:on|off))",
+        ] {
+            assert!(OnePass::new(&compile(expr).unwrap()).is_none(), "{expr}");
+        }
+    }
+
+    #[test]
+    fn test_captures() {
+        let cases = [
+            (
+                "(key=([a-z]+);)",
+                vec!["key=abc;", "key=;", "key=ab", "key=aβ;"],
+            ),
+            (
+                "(([a-z]+)=(?:on|yes);?)",
+                vec!["x=on", "x=yes;", "x=o", "x=onx"],
+            ),
+            ("((a|b)*c)", vec!["ababc", "c", "abd"]),
+            ("(x(?:y|z)?$)", vec!["x", "xz", "xzz", "xa"]),
+            ("(a+?)", vec!["aaa", "b"]),
+            (
+                "((?P<n>[0-9]+)(\\.[0-9]*)?)",
+                vec!["12.5", "12", "12.", ".5"],
+            ),
+            ("(^(?:[a-z]|λ)+)", vec!["aλb", "λλ1", "1"]),
+        ];
+        for (expr, inputs) in cases {
+            let prog = compile(expr).unwrap();
+            let one_pass = OnePass::new(&prog).unwrap();
+            let interpreter = Interpreter::new(compile(expr).unwrap());
+            for input in inputs {
+                assert_eq!(
+                    one_pass.captures(input),
+                    interpreter.backtracking_captures(input),
+                    "{expr} on {input:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_step_limit() {
+        let prog = Builder::new().max_steps(10).build("(a*b)").unwrap();
+        let one_pass = OnePass::new(&prog).unwrap();
+        assert_eq!(one_pass.try_is_match("aab"), Ok(true));
+        assert_eq!(
+            one_pass.try_captures(&"a".repeat(20)),
+            Err(MatchError::StepLimitExceeded(10))
+        );
+        assert!(!one_pass.is_match(&"a".repeat(20)));
+    }
+
+    #[test]
+    fn test_leftmost_longest() {
+        let one_pass = OnePass::new(&compile("(a*|b)").unwrap()).unwrap();
+        assert_eq!(
+            one_pass.captures("b"),
+            Some(vec![Some((0, 0)), Some((0, 0))])
+        );
+
+        let prog = Builder::new()
+            .syntax(Syntax::PosixExtended)
+            .build("a*|b")
+            .unwrap();
+        let one_pass = OnePass::new(&prog).unwrap();
+        assert_eq!(one_pass.captures("b"), Some(vec![Some((0, 1))]));
+        assert_eq!(one_pass.captures("x"), Some(vec![Some((0, 0))]));
+    }
+}
@@ -0,0 +1,103 @@
+use std::ops::Deref;
+
+use super::{aho_corasick::AhoCorasick, compiler::Literals, inst::Inst};
+
+/// A compiled pattern together with its capture group metadata.
+pub struct Program {
+    insts: Vec<Inst>,
+    capture_names: Vec<Option<String>>,
+    leftmost_longest: bool,
+    reversed: bool,
+    max_steps: Option<u64>,
+    literals: Literals,
+    aho_corasick: Option<AhoCorasick>,
+}
+
+impl Program {
+    pub fn new(insts: Vec<Inst>, capture_names: Vec<Option<String>>) -> Self {
+        Self {
+            insts,
+            capture_names,
+            leftmost_longest: false,
+            reversed: false,
+            max_steps: None,
+            literals: Literals::default(),
+            aho_corasick: None,
+        }
+    }
+
+    /// Prefers the longest match, as POSIX requires.
+    pub fn with_leftmost_longest(mut self, yes: bool) -> Self {
+        self.leftmost_longest = yes;
+        self
+    }
+
+    pub fn leftmost_longest(&self) -> bool {
+        self.leftmost_longest
+    }
+
+    /// Marks the program as matching reversed input.
+    pub fn with_reversed(mut self, yes: bool) -> Self {
+        self.reversed = yes;
+        self
+    }
+
+    pub fn reversed(&self) -> bool {
+        self.reversed
+    }
+
+    /// Step budget of the `try_` searches.
+    pub fn with_max_steps(mut self, steps: Option<u64>) -> Self {
+        self.max_steps = steps;
+        self
+    }
+
+    pub fn max_steps(&self) -> Option<u64> {
+        self.max_steps
+    }
+
+    /// Literals every match contains, used as a prefilter.
+    pub fn with_literals(mut self, literals: Literals) -> Self {
+        self.literals = literals;
+        self
+    }
+
+    pub fn literals(&self) -> &Literals {
+        &self.literals
+    }
+
+    /// Matcher run instead of the program for literal alternatives.
+    pub fn with_aho_corasick(mut self, aho_corasick: AhoCorasick) -> Self {
+        self.aho_corasick = Some(aho_corasick);
+        self
+    }
+
+    pub fn aho_corasick(&self) -> Option<&AhoCorasick> {
+        self.aho_corasick.as_ref()
+    }
+
+    /// Includes the implicit group 0.
+    pub fn captures_len(&self) -> usize {
+        self.capture_names.len()
+    }
+
+    /// Name of every capture group by index, `None` for unnamed groups.
+    pub fn capture_names(&self) -> impl Iterator<Item = Option<&str>> {
+        self.capture_names.iter().map(|name| name.as_deref())
+    }
+
+    /// Index of the capture group called `name`.
+    pub fn capture_index(&self, name: &str) -> Option<usize> {
+        self.capture_names
+            .iter()
+            .position(|name1| name1.as_deref() == Some(name))
+    }
+}
+
+impl Deref for Program {
+    type Target = [Inst];
+
+    fn deref(&self) -> &[Inst] {
+        &self.insts
+    }
+}
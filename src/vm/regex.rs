@@ -0,0 +1,354 @@
+use memchr::memmem::Finder;
+
+use crate::automa::{BitParallel, Dfa};
+
+use super::{
+    compiler::{Builder, CompileError},
+    error::MatchError,
+    interpreter::{has_backrefs, Captures, Fuel, Interpreter},
+    onepass::OnePass,
+    program::Program,
+};
+
+/// Shortest input worth running the automaton on, in bytes.
+const DFA_MIN_LEN: usize = 256;
+
+/// Compiled pattern picking the engine for every search.
+pub struct Regex {
+    interpreter: Interpreter,
+    one_pass: Option<OnePass>,
+    /// Automaton matching the whole inputs that contain a match.
+    dfa: Option<Dfa>,
+    bit_parallel: Option<BitParallel>,
+    backrefs: bool,
+    prefix: Finder<'static>,
+}
+
+/// Engine a [`Regex`] runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Aho-Corasick over the literal alternatives the pattern consists of
+    Literals,
+    BitParallel,
+    Dfa,
+    /// Thompson VM started at the occurrences of the literal prefix
+    Thompson,
+    OnePass,
+    Backtracking,
+}
+
+impl Regex {
+    pub fn new(expr: &str) -> Result<Self, CompileError> {
+        Builder::new().build_regex(expr)
+    }
+
+    pub(super) fn from_parts(prog: Program, dfa: Option<Dfa>) -> Self {
+        let one_pass = OnePass::new(&prog);
+        let bit_parallel = dfa.as_ref().and_then(Dfa::bit_parallel);
+        let backrefs = has_backrefs(&prog);
+        let prefix = Finder::new(prog.literals().prefix()).into_owned();
+        Self {
+            interpreter: Interpreter::new(prog),
+            one_pass,
+            dfa,
+            bit_parallel,
+            backrefs,
+            prefix,
+        }
+    }
+
+    pub fn program(&self) -> &Program {
+        self.interpreter.program()
+    }
+
+    /// Engine [`Self::is_match`] runs on `input`.
+    pub fn strategy(&self, input: &str) -> Strategy {
+        if self.backrefs {
+            Strategy::Backtracking
+        } else if self.program().aho_corasick().is_some() {
+            Strategy::Literals
+        } else if self.bit_parallel.is_some() {
+            Strategy::BitParallel
+        } else if self.dfa.is_some() && input.len() >= DFA_MIN_LEN {
+            Strategy::Dfa
+        } else {
+            Strategy::Thompson
+        }
+    }
+
+    /// Engine [`Self::captures`] tries first.
+    pub fn captures_strategy(&self) -> Strategy {
+        if self.one_pass.is_some() {
+            Strategy::OnePass
+        } else {
+            Strategy::Backtracking
+        }
+    }
+
+    /// Whether a match starts anywhere in `input`.
+    pub fn is_match(&self, input: &str) -> bool {
+        self.is_match_with(input, &Fuel::new(None))
+    }
+
+    /// Byte span of the leftmost match.
+    pub fn find(&self, input: &str) -> Option<(usize, usize)> {
+        self.captures(input)?[0]
+    }
+
+    /// Byte span of every capture group of the leftmost match.
+    pub fn captures(&self, input: &str) -> Option<Captures> {
+        self.captures_with(input, &Fuel::new(None))
+    }
+
+    /// Same as [`Self::is_match`], failing once out of steps.
+    pub fn try_is_match(&self, input: &str) -> Result<bool, MatchError> {
+        let fuel = Fuel::new(self.program().max_steps());
+        let matched = self.is_match_with(input, &fuel);
+        fuel.check(matched)
+    }
+
+    /// Same as [`Self::find`], failing once the step budget is spent.
+    pub fn try_find(&self, input: &str) -> Result<Option<(usize, usize)>, MatchError> {
+        Ok(self.try_captures(input)?.and_then(|captures| captures[0]))
+    }
+
+    /// Same as [`Self::captures`], failing once the step budget is spent.
+    pub fn try_captures(&self, input: &str) -> Result<Option<Captures>, MatchError> {
+        let fuel = Fuel::new(self.program().max_steps());
+        let captures = self.captures_with(input, &fuel);
+        fuel.check(captures)
+    }
+
+    fn is_match_with(&self, input: &str, fuel: &Fuel) -> bool {
+        match self.strategy(input) {
+            Strategy::Literals => self
+                .program()
+                .aho_corasick()
+                .is_some_and(|aho_corasick| aho_corasick.is_match(input)),
+            Strategy::BitParallel => self
+                .bit_parallel
+                .as_ref()
+                .is_some_and(|bit_parallel| bit_parallel.test(input)),
+            Strategy::Dfa => self.dfa.as_ref().is_some_and(|dfa| dfa.test(input)),
+            Strategy::Thompson => self.interpreter.thompson_search_with(input, fuel),
+            Strategy::OnePass | Strategy::Backtracking => self.leftmost(input, fuel).is_some(),
+        }
+    }
+
+    fn captures_with(&self, input: &str, fuel: &Fuel) -> Option<Captures> {
+        if !self.backrefs && !self.is_match_with(input, fuel) {
+            return None;
+        }
+        self.leftmost(input, fuel)
+    }
+
+    /// One-pass at the first candidate, then a single unanchored pass.
+    fn leftmost(&self, input: &str, fuel: &Fuel) -> Option<Captures> {
+        let mut start = self.prefix.find(input.as_bytes())?;
+        if let Some(one_pass) = &self.one_pass {
+            let captures = one_pass.captures_at(input, start, fuel);
+            if captures.is_some() || fuel.is_spent() {
+                return captures;
+            }
+            start += input[start..].chars().next()?.len_utf8();
+        }
+        self.interpreter.leftmost_captures_at(input, start, fuel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vm::{Builder, Interpreter, Syntax};
+
+    use super::*;
+
+    /// Takes a DFA of about `2^n` states.
+    fn nth_last_a(n: usize) -> String {
+        format!("((a|b)*a{})", "(a|b)".repeat(n))
+    }
+
+    #[test]
+    fn test_strategy() {
+        let long = "x".repeat(DFA_MIN_LEN);
+        let cases = [
+            (
+                "(foo|bar)".to_string(),
+                Strategy::Literals,
+                Strategy::Literals,
+            ),
+            (
+                "(ab+c)".to_string(),
+                Strategy::BitParallel,
+                Strategy::BitParallel,
+            ),
+            (
+                "(^ab+c)".to_string(),
+                Strategy::Thompson,
+                Strategy::Thompson,
+            ),
+            (nth_last_a(6), Strategy::Thompson, Strategy::Dfa),
+            (nth_last_a(7), Strategy::Thompson, Strategy::Thompson),
+        ];
+        for (expr, short, long_input) in cases {
+            let regex = Regex::new(&expr).unwrap();
+            assert_eq!(regex.strategy("x"), short, "{expr}");
+            assert_eq!(regex.strategy(&long), long_input, "{expr}");
+        }
+
+        let regex = Builder::new()
+            .backtracking(true)
+            .build_regex(r"((a|b)\2)")
+            .unwrap();
+        assert_eq!(regex.strategy("x"), Strategy::Backtracking);
+        assert_eq!(regex.captures_strategy(), Strategy::Backtracking);
+        let regex = Regex::new("(key=([a-z]+);)").unwrap();
+        assert_eq!(regex.captures_strategy(), Strategy::OnePass);
+    }
+
+    #[test]
+    fn test_engines_agree() {
+        let long = format!("{}abbab{}", "ab".repeat(200), "x".repeat(100));
+        let long_reversed: String = long.chars().rev().collect();
+        let inputs = [
+            "",
+            "xabcx",
+            "xcbax",
+            "ac",
+            "foo",
+            "oof",
+            "a=b;key=xy; key=z;",
+            ";yx=yek KEY=AB;",
+            long.as_str(),
+            long_reversed.as_str(),
+        ];
+        let exprs = [
+            "(foo|bar)",
+            "(ab+c)",
+            "(^ab+c)",
+            &nth_last_a(6),
+            "(key=([a-z]+);)",
+            "((?i)KEY=([a-z0-9_]+);)",
+            "((?<=a)b+)",
+        ];
+        let posix_exprs = [
+            "foo|bar",
+            "ab+c",
+            "^ab+c",
+            &nth_last_a(6),
+            "key=([a-z]+);",
+            "(a|ab)(c|bcd)",
+            "[[:alpha:]]+=x",
+        ];
+        let mut reverse = Builder::new();
+        reverse.reverse(true);
+        let mut case_insensitive = Builder::new();
+        case_insensitive.case_insensitive(true);
+        let mut posix = Builder::new();
+        posix.syntax(Syntax::PosixExtended);
+        let cases = [
+            (Builder::new(), &exprs),
+            (reverse, &exprs),
+            (case_insensitive, &exprs),
+            (posix, &posix_exprs),
+        ];
+        for (builder, exprs) in cases {
+            for expr in exprs {
+                let regex = builder.build_regex(expr).unwrap();
+                let interpreter = Interpreter::new(builder.build(expr).unwrap());
+                for input in inputs {
+                    let expected = interpreter.thompson_search(input);
+                    let context = format!("{expr} on {input:?} with {builder:?}");
+                    assert_eq!(regex.is_match(input), expected, "{context}");
+                    assert_eq!(regex.find(input).is_some(), expected, "{context}");
+                }
+            }
+        }
+
+        // Same engines as a forward search of the reversed input
+        let mut reverse = Builder::new();
+        reverse.reverse(true);
+        let regex = reverse.build_regex("(ab)").unwrap();
+        assert!(!regex.is_match("ab"));
+        assert_eq!(regex.find("ab"), None);
+        assert_eq!(regex.find("xba"), Some((1, 3)));
+    }
+
+    #[test]
+    fn test_captures() {
+        let regex = Regex::new("(key=([a-z]+);)").unwrap();
+        assert_eq!(
+            regex.captures("a=b; key=xy; key=z;"),
+            Some(vec![Some((5, 12)), Some((5, 12)), Some((9, 11))])
+        );
+        assert_eq!(regex.captures("key=;"), None);
+
+        let regex = Regex::new("(fo+|ba(r|z))").unwrap();
+        assert_eq!(regex.find("a baz fooo"), Some((2, 5)));
+
+        // Starts after the first candidate are tried in a single pass
+        let input = format!("{}abc", "ab ".repeat(100_000));
+        let len = input.len();
+        let regex = Regex::new("((a|ab)c)").unwrap();
+        assert_eq!(regex.captures_strategy(), Strategy::Backtracking);
+        assert_eq!(
+            regex.captures(&input),
+            Some(vec![
+                Some((len - 3, len)),
+                Some((len - 3, len)),
+                Some((len - 3, len - 1))
+            ])
+        );
+        let regex = Builder::new()
+            .syntax(Syntax::PosixExtended)
+            .build_regex("x|a|ab|b")
+            .unwrap();
+        assert_eq!(regex.find("cab"), Some((1, 3)));
+        assert_eq!(regex.find(&input), Some((0, 2)));
+        let regex = Regex::new("(a(b|c)+d)").unwrap();
+        assert_eq!(regex.captures_strategy(), Strategy::OnePass);
+        let input = format!("{}abd", "abc".repeat(100_000));
+        let len = input.len();
+        assert_eq!(regex.find(&input), Some((len - 3, len)));
+
+        let regex = Builder::new()
+            .backtracking(true)
+            .build_regex(r"((a|b)\2)")
+            .unwrap();
+        assert!(regex.is_match("abba"));
+        assert_eq!(regex.find("abba"), Some((1, 3)));
+        assert!(!regex.is_match("abab"));
+    }
+
+    #[test]
+    fn test_step_limit() {
+        let regex = Builder::new()
+            .max_steps(100)
+            .build_regex("(^(a|b)*(c|d))")
+            .unwrap();
+        let input = "a".repeat(1000);
+        assert_eq!(regex.strategy(&input), Strategy::Thompson);
+        assert_eq!(
+            regex.try_is_match(&input),
+            Err(MatchError::StepLimitExceeded(100))
+        );
+        assert_eq!(regex.try_find("abc"), Ok(Some((0, 3))));
+        assert!(!regex.is_match(&input));
+
+        // Linear engines take no steps
+        let regex = Builder::new().max_steps(1).build_regex("(ab+c)").unwrap();
+        assert_eq!(regex.try_is_match(&input), Ok(false));
+
+        // Finding the captures takes some
+        let regex = Builder::new()
+            .max_steps(1000)
+            .build_regex("(a+c|b)")
+            .unwrap();
+        let input = format!("{} b", "a".repeat(40_000));
+        assert_eq!(regex.try_is_match(&input), Ok(true));
+        assert_eq!(
+            regex.try_find(&input),
+            Err(MatchError::StepLimitExceeded(1000))
+        );
+        assert_eq!(regex.find(&input), Some((40_001, 40_002)));
+    }
+}